@@ -0,0 +1,246 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The IP version, which picks between ICMP and ICMPv6
+#[derive(Clone, Copy, PartialEq)]
+pub enum IpVersion {
+    V4,
+    V6,
+}
+
+impl IpVersion {
+    /// ICMP type of an echo request
+    fn echo_request_type(self) -> u8 {
+        match self {
+            IpVersion::V4 => 8,
+            IpVersion::V6 => 128,
+        }
+    }
+
+    /// ICMP type of an echo reply
+    fn echo_reply_type(self) -> u8 {
+        match self {
+            IpVersion::V4 => 0,
+            IpVersion::V6 => 129,
+        }
+    }
+}
+
+/// Type, code, checksum, identifier and sequence number
+pub const HEADER_LEN: usize = 8;
+/// The send time at the start of the payload: seconds and microseconds
+/// since the epoch, like a struct timeval
+const TIMESTAMP_LEN: usize = 16;
+/// The payload size iputils ping sends by default
+pub const DEFAULT_PAYLOAD_LEN: usize = 56;
+
+/// The Internet checksum of RFC 1071: the one's complement of the one's
+/// complement sum of the data as 16 bit big endian words
+pub fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = 0;
+    let mut words = data.chunks_exact(2);
+    for word in &mut words {
+        sum += u32::from(u16::from_be_bytes([word[0], word[1]]));
+    }
+    // An odd byte is padded with a zero byte
+    if let [last] = words.remainder() {
+        sum += u32::from(u16::from_be_bytes([*last, 0]));
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Build an echo request with payload_len bytes of payload. The payload
+/// starts with the current time, if it fits, and the rest of each byte
+/// holds its offset in the payload, like iputils ping.
+pub fn echo_request(
+    version: IpVersion,
+    identifier: u16,
+    sequence: u16,
+    payload_len: usize,
+) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(HEADER_LEN + payload_len);
+    packet.extend_from_slice(&[version.echo_request_type(), 0, 0, 0]);
+    packet.extend_from_slice(&identifier.to_be_bytes());
+    packet.extend_from_slice(&sequence.to_be_bytes());
+
+    if payload_len >= TIMESTAMP_LEN {
+        // The clock being before 1970 isn't worth failing over
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        packet.extend_from_slice(&now.as_secs().to_be_bytes());
+        packet.extend_from_slice(&u64::from(now.subsec_micros()).to_be_bytes());
+    }
+    let padding = packet.len() - HEADER_LEN..payload_len;
+    packet.extend(padding.map(|i| i as u8));
+
+    // The ICMPv6 checksum covers the IPv6 addresses as well and is filled
+    // in by the kernel
+    if version == IpVersion::V4 {
+        let sum = checksum(&packet);
+        packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    }
+    packet
+}
+
+/// An echo reply to one of our requests
+pub struct EchoReply {
+    pub sequence: u16,
+    /// Size of the ICMP message, without the IP header
+    pub len: usize,
+}
+
+/// Split a packet read from a raw IPv4 socket into the time to live of its
+/// IPv4 header and the ICMP message following the header
+pub fn strip_ipv4_header(packet: &[u8]) -> Option<(u8, &[u8])> {
+    let version = packet.first()? >> 4;
+    let header_len = usize::from(packet.first()? & 0x0f) * 4;
+    if version != 4 || header_len < 20 {
+        return None;
+    }
+    let message = packet.get(header_len..)?;
+    Some((packet[8], message))
+}
+
+/// Parse an ICMP message. Returns None unless it's an intact echo reply
+/// with our identifier: raw sockets also see our own requests on
+/// loopback, other ICMP messages and the replies to other ping processes.
+/// The identifier isn't checked if it's None, for ping sockets where the
+/// kernel picks it and only passes on the replies with it.
+pub fn parse_echo_reply(
+    version: IpVersion,
+    message: &[u8],
+    identifier: Option<u16>,
+) -> Option<EchoReply> {
+    if message.len() < HEADER_LEN || message[0] != version.echo_reply_type() || message[1] != 0 {
+        return None;
+    }
+    // The checksum of a message including its checksum is 0. The kernel
+    // drops ICMPv6 messages with a wrong checksum.
+    if version == IpVersion::V4 && checksum(message) != 0 {
+        return None;
+    }
+    let reply_identifier = u16::from_be_bytes([message[4], message[5]]);
+    if identifier.is_some_and(|identifier| identifier != reply_identifier) {
+        return None;
+    }
+
+    Some(EchoReply {
+        sequence: u16::from_be_bytes([message[6], message[7]]),
+        len: message.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An echo reply as a host would send it back for echo_request
+    fn echo_reply(version: IpVersion, identifier: u16, sequence: u16) -> Vec<u8> {
+        let mut message = echo_request(version, identifier, sequence, DEFAULT_PAYLOAD_LEN);
+        message[0] = version.echo_reply_type();
+        message[2..4].copy_from_slice(&[0, 0]);
+        let sum = checksum(&message);
+        message[2..4].copy_from_slice(&sum.to_be_bytes());
+        message
+    }
+
+    /// Verify the checksum against the example of RFC 1071, with odd
+    /// lengths padded with a zero byte
+    #[test]
+    fn test_checksum() {
+        let data = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+        assert_eq!(checksum(&data), !0xddf2);
+        assert_eq!(checksum(&[]), 0xffff);
+        assert_eq!(checksum(&[0x01]), !0x0100);
+        assert_eq!(checksum(&data[..3]), !0xf201);
+        // The carries are folded back in
+        assert_eq!(checksum(&[0xff, 0xff, 0x00, 0x02]), !0x0002);
+    }
+
+    /// Verify echo requests carry their fields and checksum to 0
+    #[test]
+    fn test_echo_request() {
+        let request = echo_request(IpVersion::V4, 0x1234, 7, DEFAULT_PAYLOAD_LEN);
+        assert_eq!(request.len(), HEADER_LEN + DEFAULT_PAYLOAD_LEN);
+        assert_eq!(request[..2], [8, 0]);
+        assert_eq!(request[4..8], [0x12, 0x34, 0, 7]);
+        assert_eq!(checksum(&request), 0);
+        // The padding after the timestamp holds its offset in the payload
+        assert_eq!(request[HEADER_LEN + TIMESTAMP_LEN], TIMESTAMP_LEN as u8);
+        assert_eq!(*request.last().unwrap(), (DEFAULT_PAYLOAD_LEN - 1) as u8);
+
+        // Odd lengths and payloads too short for the timestamp
+        let request = echo_request(IpVersion::V4, 0x1234, 7, 5);
+        assert_eq!(request[HEADER_LEN..], [0, 1, 2, 3, 4]);
+        assert_eq!(checksum(&request), 0);
+
+        // The kernel fills in the ICMPv6 checksum
+        let request = echo_request(IpVersion::V6, 0x1234, 7, DEFAULT_PAYLOAD_LEN);
+        assert_eq!(request[..4], [128, 0, 0, 0]);
+    }
+
+    /// Verify only intact echo replies with our identifier are accepted
+    #[test]
+    fn test_parse_echo_reply() {
+        let reply = echo_reply(IpVersion::V4, 0x1234, 7);
+        let parsed = parse_echo_reply(IpVersion::V4, &reply, Some(0x1234)).unwrap();
+        assert_eq!(parsed.sequence, 7);
+        assert_eq!(parsed.len, HEADER_LEN + DEFAULT_PAYLOAD_LEN);
+        // Ping sockets don't check the identifier
+        assert!(parse_echo_reply(IpVersion::V4, &reply, None).is_some());
+
+        // Replies to another ping process
+        assert!(parse_echo_reply(IpVersion::V4, &reply, Some(0x4321)).is_none());
+        // Our own request, seen on loopback by a raw socket
+        let request = echo_request(IpVersion::V4, 0x1234, 7, DEFAULT_PAYLOAD_LEN);
+        assert!(parse_echo_reply(IpVersion::V4, &request, Some(0x1234)).is_none());
+        // A code other than 0
+        let mut wrong_code = reply.clone();
+        wrong_code[1] = 1;
+        assert!(parse_echo_reply(IpVersion::V4, &wrong_code, Some(0x1234)).is_none());
+        // A corrupted message
+        let mut corrupted = reply.clone();
+        corrupted[HEADER_LEN] ^= 0xff;
+        assert!(parse_echo_reply(IpVersion::V4, &corrupted, Some(0x1234)).is_none());
+        // A truncated header
+        assert!(parse_echo_reply(IpVersion::V4, &reply[..HEADER_LEN - 1], None).is_none());
+        assert!(parse_echo_reply(IpVersion::V4, &[], None).is_none());
+
+        // ICMPv6 has its own types, and the checksum isn't checked
+        let mut reply = echo_reply(IpVersion::V6, 0x1234, 7);
+        reply[2..4].copy_from_slice(&[0, 0]);
+        let parsed = parse_echo_reply(IpVersion::V6, &reply, Some(0x1234)).unwrap();
+        assert_eq!(parsed.sequence, 7);
+        assert!(parse_echo_reply(IpVersion::V4, &reply, Some(0x1234)).is_none());
+    }
+
+    /// Verify the IPv4 header is stripped whatever its length
+    #[test]
+    fn test_strip_ipv4_header() {
+        let reply = echo_reply(IpVersion::V4, 0x1234, 7);
+        let mut header = vec![0u8; 20];
+        header[0] = 0x45;
+        header[8] = 64;
+        let packet = [header.clone(), reply.clone()].concat();
+        let (ttl, message) = strip_ipv4_header(&packet).unwrap();
+        assert_eq!(ttl, 64);
+        assert_eq!(message, reply);
+
+        // An IHL of 6 words means 4 bytes of options
+        header[0] = 0x46;
+        header.extend_from_slice(&[1, 1, 1, 0]);
+        let packet = [header.clone(), reply.clone()].concat();
+        let (ttl, message) = strip_ipv4_header(&packet).unwrap();
+        assert_eq!(ttl, 64);
+        assert_eq!(message, reply);
+
+        // Not IPv4, an IHL below 5 and a packet shorter than its header
+        assert!(strip_ipv4_header(&[0x65; 28]).is_none());
+        assert!(strip_ipv4_header(&[0x44; 28]).is_none());
+        assert!(strip_ipv4_header(&header[..22]).is_none());
+        assert!(strip_ipv4_header(&[]).is_none());
+    }
+}
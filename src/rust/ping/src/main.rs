@@ -1,75 +1,360 @@
-use nix::sys::socket::recvfrom;
-use nix::sys::socket::sendto;
-use nix::sys::socket::AddressFamily;
-use nix::sys::socket::SockType;
-use nix::sys::socket::{socket, MsgFlags, SockFlag, SockProtocol, SockaddrIn};
-use simpleargs::{Arg, ArgType, Parser};
-use std::net::Ipv4Addr;
-use std::os::fd::AsRawFd;
-
-fn parse_args() -> Parser {
+mod icmp;
+mod socket;
+mod stats;
+
+use icmp::IpVersion;
+use nix::errno::Errno;
+use nix::libc::c_int;
+use nix::net::if_::if_indextoname;
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use simpleargs::{Arg, ArgConfig, ArgType, ChoiceParser, Matches};
+use socket::{IcmpSocket, SocketMode};
+use std::collections::HashMap;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+fn parse_args() -> Matches {
     simpleargs::new("ping".to_string())
+        .add_arg(
+            ArgConfig::new("count".to_string())
+                .short_flag('c')
+                .arg_type(ArgType::Unsigned32)
+                .range(1u32..)
+                .description("Stop after sending count packets".to_string()),
+        )
+        .add_arg(
+            ArgConfig::new("interval".to_string())
+                .short_flag('i')
+                .arg_type(ArgType::Double)
+                .range(0.002..)
+                .default_value(Arg::from(1.0))
+                .description("Seconds to wait between sending packets".to_string()),
+        )
+        .add_arg(
+            ArgConfig::new("timeout".to_string())
+                .short_flag('W')
+                .arg_type(ArgType::Double)
+                .range(0.001..)
+                .default_value(Arg::from(10.0))
+                .description("Seconds to wait for each reply".to_string()),
+        )
+        .add_arg(
+            ArgConfig::new("deadline".to_string())
+                .short_flag('w')
+                .arg_type(ArgType::Unsigned32)
+                .range(1u32..)
+                .description(
+                    "Stop after deadline seconds, however many packets were sent".to_string(),
+                ),
+        )
+        .add_arg(
+            ArgConfig::new("socket".to_string())
+                .long_flag("socket".to_string())
+                .arg_type(ArgType::custom(ChoiceParser::of::<SocketMode>()))
+                .default_value(Arg::from("auto"))
+                .description(
+                    "The kind of ICMP socket: auto tries an unprivileged ping socket \
+                     before a raw socket"
+                        .to_string(),
+                ),
+        )
+        .add_arg(
+            ArgConfig::new("ipv4".to_string())
+                .short_flag('4')
+                .conflicts_with("ipv6".to_string())
+                .description("Only ping IPv4 addresses".to_string()),
+        )
+        .add_arg(
+            ArgConfig::new("ipv6".to_string())
+                .short_flag('6')
+                .description("Only ping IPv6 addresses".to_string()),
+        )
         .add_flag(
             "dst_ip".to_string(),
             None,
             None,
             true,
             Some(ArgType::String),
-            "The target IP to ping".to_string(),
+            "The host to ping, e.g. 192.0.2.1, ::1 or fe80::1%eth0".to_string(),
         )
         .parse(std::env::args())
 }
 
+/// Look up host, a name or an address with an optional scope id such as
+/// fe80::1%eth0, picking the first address of the IP version if given
+fn resolve(host: &str, version: Option<IpVersion>) -> Result<SocketAddr, String> {
+    // ICMP doesn't use ports
+    let addrs = (host, 0).to_socket_addrs().map_err(|e| e.to_string())?;
+    let mut addrs = addrs.filter(|addr| match version {
+        Some(IpVersion::V4) => addr.is_ipv4(),
+        Some(IpVersion::V6) => addr.is_ipv6(),
+        None => true,
+    });
+    addrs.next().ok_or_else(|| match version {
+        Some(IpVersion::V4) => "no IPv4 address".to_string(),
+        Some(IpVersion::V6) => "no IPv6 address".to_string(),
+        None => "no address".to_string(),
+    })
+}
+
+/// Format an address like iputils, with the interface of a link-local
+/// IPv6 address as in fe80::1%eth0
+fn display_address(addr: &SocketAddr) -> String {
+    match addr {
+        SocketAddr::V6(addr) if addr.scope_id() != 0 => {
+            let interface = if_indextoname(addr.scope_id())
+                .ok()
+                .and_then(|name| name.into_string().ok())
+                // An unknown index may come back as an empty name
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| addr.scope_id().to_string());
+            format!("{}%{}", addr.ip(), interface)
+        }
+        _ => addr.ip().to_string(),
+    }
+}
+
+/// Set by SIGINT to stop pinging
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// Set by SIGQUIT to print the statistics so far
+static STATUS_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_signal(signal: c_int) {
+    if signal == Signal::SIGQUIT as c_int {
+        STATUS_REQUESTED.store(true, Ordering::SeqCst);
+    } else {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Handle SIGINT and SIGQUIT with handle_signal. Without SA_RESTART, they
+/// interrupt a blocked recvfrom so that the main loop sees them at once.
+fn install_signal_handlers() -> nix::Result<()> {
+    let action = SigAction::new(
+        SigHandler::Handler(handle_signal),
+        SaFlags::empty(),
+        SigSet::empty(),
+    );
+    for signal in [Signal::SIGINT, Signal::SIGQUIT] {
+        // Safe because the handler only stores to atomics
+        unsafe { sigaction(signal, &action) }?;
+    }
+    Ok(())
+}
+
+/// When to send and how long to wait, from the command line
+struct Options {
+    count: Option<u32>,
+    interval: Duration,
+    timeout: Duration,
+    deadline: Option<Duration>,
+}
+
+impl Options {
+    fn from_matches(p: &Matches) -> Result<Self, simpleargs::Error> {
+        Ok(Options {
+            count: p.get("count")?,
+            interval: Duration::from_secs_f64(p.get_or("interval", 1.0)?),
+            timeout: Duration::from_secs_f64(p.get_or("timeout", 10.0)?),
+            deadline: p
+                .get::<u32>("deadline")?
+                .map(|secs| Duration::from_secs(secs.into())),
+        })
+    }
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let p = parse_args();
 
-    let dst_ip = match p.get_arg("dst_ip") {
-        Some(Arg::String(dst)) => dst.to_owned(),
-        _ => "127.0.0.1".to_string(),
+    // Unwrap is safe here because dst_ip is required
+    let dst_ip: String = p.get("dst_ip")?.unwrap();
+    let options = Options::from_matches(&p)?;
+    let socket_mode = p.get_or("socket", SocketMode::Auto)?;
+    let requested_version = if p.get_or("ipv6", false)? {
+        Some(IpVersion::V6)
+    } else if p.get_or("ipv4", false)? {
+        Some(IpVersion::V4)
+    } else {
+        None
     };
 
-    let target_ip = dst_ip.parse::<Ipv4Addr>().unwrap();
-    let target_ip_octets = target_ip.octets();
-    let target_addr = SockaddrIn::new(
-        target_ip_octets[0],
-        target_ip_octets[1],
-        target_ip_octets[2],
-        target_ip_octets[3],
-        0,
-    ); // ICMP doesn't use ports
-
-    // Create a raw socket for ICMP
-    let sock = socket(
-        AddressFamily::Inet,
-        SockType::Raw,
-        SockFlag::empty(),
-        Some(SockProtocol::Icmp),
-    )?;
-
-    // Construct a simple ICMP echo request
-    let mut data: Vec<u8> = vec![0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
-    // Dummy sequence number and checksum (replace with actual values)
-    data.extend_from_slice(&[0x01, 0x00, 0x00, 0x00]);
-
-    // Send the ICMP echo request
-    sendto(sock.as_raw_fd(), &data, &target_addr, MsgFlags::empty())?;
-
-    println!("ICMP echo request sent to {}", target_ip);
-
-    // Optionally receive a response (non-blocking)
-    let mut buf = vec![0u8; 1024];
-    match recvfrom::<SockaddrIn>(sock.as_raw_fd(), &mut buf) {
-        Ok((size, _)) => {
-            println!("Received {} bytes of data", size);
-            // Process the received data (e.g., parse ICMP echo reply)
-        }
-        Err(nix::errno::Errno::EWOULDBLOCK) => {
-            println!("No response received within timeout.");
+    let target_addr = match resolve(&dst_ip, requested_version) {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!("ping: {}: {}", dst_ip, e);
+            std::process::exit(2);
         }
+    };
+    let version = match target_addr {
+        SocketAddr::V4(_) => IpVersion::V4,
+        SocketAddr::V6(_) => IpVersion::V6,
+    };
+
+    let sock = match IcmpSocket::open(socket_mode, version) {
+        Ok(sock) => sock,
         Err(e) => {
-            println!("Error receiving data: {:?}", e);
+            eprintln!("ping: {}", e);
+            std::process::exit(2);
         }
+    };
+
+    // Replies are told apart from those to other ping processes by the
+    // identifier
+    let identifier = std::process::id() as u16;
+    let reply_identifier = sock.reply_identifier(identifier);
+    let payload_len = icmp::DEFAULT_PAYLOAD_LEN;
+
+    let target = display_address(&target_addr);
+    match version {
+        IpVersion::V4 => println!(
+            "PING {} ({}) {}({}) bytes of data.",
+            dst_ip,
+            target,
+            payload_len,
+            payload_len + icmp::HEADER_LEN + 20
+        ),
+        IpVersion::V6 => println!("PING {} ({}) {} data bytes", dst_ip, target, payload_len),
     }
 
+    install_signal_handlers()?;
+
+    let start = Instant::now();
+    let deadline = options.deadline.map(|deadline| start + deadline);
+    let mut stats = stats::Statistics::default();
+    let mut next_send = start;
+    // Send times of the requests still waiting for a reply, by sequence
+    let mut pending: HashMap<u16, Instant> = HashMap::new();
+    let mut buf = vec![0u8; 65536];
+
+    loop {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            break;
+        }
+        if STATUS_REQUESTED.swap(false, Ordering::SeqCst) {
+            eprintln!("{}", stats.interim());
+        }
+        let now = Instant::now();
+        if deadline.is_some_and(|deadline| now >= deadline) {
+            break;
+        }
+        // Replies arriving after the timeout are ignored
+        pending.retain(|_, sent| now - *sent < options.timeout);
+
+        let sending = options
+            .count
+            .is_none_or(|count| stats.transmitted() < count);
+        if sending && now >= next_send {
+            // The sequence starts at 1 and wraps around like iputils
+            let sequence = (stats.transmitted() + 1) as u16;
+            let data = icmp::echo_request(version, identifier, sequence, payload_len);
+            // A failed send counts as a lost packet, like iputils, so
+            // that pinging goes on if the route comes back
+            match sock.send_to(&data, target_addr) {
+                Ok(_) => {
+                    pending.insert(sequence, now);
+                }
+                Err(e) => eprintln!("ping: sendmsg: {}", e.desc()),
+            }
+            stats.add_transmitted();
+            next_send = now + options.interval;
+            continue;
+        }
+        if !sending && pending.is_empty() {
+            break;
+        }
+
+        // Wait for a reply until the next packet is due, or after the last
+        // one until the replies time out
+        let mut wake = if sending {
+            next_send
+        } else {
+            // Unwrap is safe here because pending isn't empty
+            *pending.values().min().unwrap() + options.timeout
+        };
+        if let Some(deadline) = deadline {
+            wake = wake.min(deadline);
+        }
+        sock.set_timeout(wake - now)?;
+
+        let packet = match sock.recv(&mut buf) {
+            Ok(Some(packet)) => packet,
+            Ok(None) | Err(Errno::EAGAIN | Errno::EINTR) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let reply = match icmp::parse_echo_reply(version, packet.message, reply_identifier) {
+            Some(reply) => reply,
+            None => continue,
+        };
+        // Only the first reply to each of our requests counts
+        let sent = match pending.remove(&reply.sequence) {
+            Some(sent) => sent,
+            None => continue,
+        };
+        let rtt = Instant::now() - sent;
+        stats.add_received(rtt);
+        let source = display_address(&packet.source.unwrap_or(target_addr));
+        let ttl = packet
+            .ttl
+            .map(|ttl| format!(" ttl={}", ttl))
+            .unwrap_or_default();
+        println!(
+            "{} bytes from {}: icmp_seq={}{} time={:.3} ms",
+            reply.len,
+            source,
+            reply.sequence,
+            ttl,
+            rtt.as_secs_f64() * 1000.0
+        );
+    }
+
+    print!("\n{}", stats.summary(&dst_ip, start.elapsed()));
+    // Exit with 1 when the host didn't answer, like iputils
+    if stats.received() == 0 {
+        std::process::exit(1);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify addresses are picked by IP version
+    #[test]
+    fn test_resolve() {
+        let addr = resolve("127.0.0.1", None).unwrap();
+        assert_eq!(addr, "127.0.0.1:0".parse().unwrap());
+        assert_eq!(
+            resolve("::1", Some(IpVersion::V6)).unwrap().to_string(),
+            "[::1]:0"
+        );
+        assert_eq!(
+            resolve("127.0.0.1", Some(IpVersion::V6)).unwrap_err(),
+            "no IPv6 address"
+        );
+        assert_eq!(
+            resolve("::1", Some(IpVersion::V4)).unwrap_err(),
+            "no IPv4 address"
+        );
+    }
+
+    /// Verify the scope id of a link-local address is named by its
+    /// interface again when shown
+    #[test]
+    fn test_scope_id() {
+        let addr = resolve("fe80::1%lo", None).unwrap();
+        let loopback = nix::net::if_::if_nametoindex("lo").unwrap();
+        match addr {
+            SocketAddr::V6(addr) => assert_eq!(addr.scope_id(), loopback),
+            SocketAddr::V4(_) => panic!("{} isn't an IPv6 address", addr),
+        }
+        assert_eq!(display_address(&addr), "fe80::1%lo");
+
+        // Unknown interface indexes are shown as numbers
+        let addr: SocketAddr = "[fe80::1%999999]:0".parse().unwrap();
+        assert_eq!(display_address(&addr), "fe80::1%999999");
+        let addr: SocketAddr = "[::1]:0".parse().unwrap();
+        assert_eq!(display_address(&addr), "::1");
+    }
+}
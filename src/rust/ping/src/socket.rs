@@ -0,0 +1,258 @@
+use nix::errno::Errno;
+use nix::libc;
+use nix::sys::socket::sockopt::ReceiveTimeout;
+use nix::sys::socket::{
+    sendto, setsockopt, socket, AddressFamily, MsgFlags, SockFlag, SockProtocol, SockType,
+    SockaddrLike, SockaddrStorage,
+};
+use nix::sys::time::{TimeVal, TimeValLike};
+use simpleargs::ValueEnum;
+use std::fmt;
+use std::mem;
+use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::time::Duration;
+
+use super::icmp::{self, IpVersion};
+
+/// Which kind of ICMP socket to open, from --socket
+#[derive(Clone, Copy, PartialEq)]
+pub enum SocketMode {
+    /// A ping socket if the user may open one, else a raw socket
+    Auto,
+    Datagram,
+    Raw,
+}
+
+impl ValueEnum for SocketMode {
+    fn variants() -> Vec<(&'static str, Self)> {
+        vec![
+            ("auto", SocketMode::Auto),
+            ("datagram", SocketMode::Datagram),
+            ("raw", SocketMode::Raw),
+        ]
+    }
+}
+
+/// Why no ICMP socket could be opened, with the error of each kind of
+/// socket tried
+pub struct OpenError {
+    datagram: Option<Errno>,
+    raw: Option<Errno>,
+}
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "can't open an ICMP socket:")?;
+        if let Some(e) = self.datagram {
+            write!(f, "\n  ping socket (SOCK_DGRAM): {}", e)?;
+        }
+        if let Some(e) = self.raw {
+            write!(f, "\n  raw socket (SOCK_RAW): {}", e)?;
+        }
+        if self.datagram.is_some() {
+            write!(
+                f,
+                "\nAllow ping sockets for your group with net.ipv4.ping_group_range, \
+                 e.g. sysctl -w net.ipv4.ping_group_range=\"0 2147483647\""
+            )?;
+        }
+        if self.raw.is_some() {
+            write!(
+                f,
+                "\nOpening raw sockets needs root or the CAP_NET_RAW capability, \
+                 e.g. setcap cap_net_raw+ep {}",
+                std::env::current_exe()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_else(|_| "ping".to_string())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A received packet
+pub struct Packet<'a> {
+    /// The ICMP message, without any IP header
+    pub message: &'a [u8],
+    pub source: Option<SocketAddr>,
+    /// Time to live of the IPv4 packet or hop limit of the IPv6 packet, if
+    /// known
+    pub ttl: Option<u8>,
+}
+
+/// A socket sending and receiving ICMP or ICMPv6 messages: a Linux ping
+/// socket (SOCK_DGRAM), which unprivileged users may open if their group
+/// is in net.ipv4.ping_group_range, or a raw socket, which needs
+/// CAP_NET_RAW.
+pub struct IcmpSocket {
+    fd: OwnedFd,
+    version: IpVersion,
+    datagram: bool,
+}
+
+impl IcmpSocket {
+    pub fn open(mode: SocketMode, version: IpVersion) -> Result<Self, OpenError> {
+        let mut error = OpenError {
+            datagram: None,
+            raw: None,
+        };
+        if mode != SocketMode::Raw {
+            match Self::open_type(SockType::Datagram, version) {
+                Ok(sock) => return Ok(sock),
+                Err(e) => error.datagram = Some(e),
+            }
+        }
+        if mode != SocketMode::Datagram {
+            match Self::open_type(SockType::Raw, version) {
+                Ok(sock) => return Ok(sock),
+                Err(e) => error.raw = Some(e),
+            }
+        }
+        Err(error)
+    }
+
+    fn open_type(sock_type: SockType, version: IpVersion) -> nix::Result<Self> {
+        let (family, protocol) = match version {
+            IpVersion::V4 => (AddressFamily::Inet, SockProtocol::Icmp),
+            IpVersion::V6 => (AddressFamily::Inet6, SockProtocol::IcmpV6),
+        };
+        let fd = socket(family, sock_type, SockFlag::empty(), Some(protocol))?;
+        let datagram = sock_type == SockType::Datagram;
+
+        // Only raw IPv4 sockets pass on the IP header, otherwise the TTL or
+        // hop limit comes as a control message
+        let option = match version {
+            IpVersion::V4 if datagram => Some((libc::IPPROTO_IP, libc::IP_RECVTTL)),
+            IpVersion::V4 => None,
+            IpVersion::V6 => Some((libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT)),
+        };
+        if let Some((level, name)) = option {
+            let enable: libc::c_int = 1;
+            // Safe because the option value is a c_int of the given size
+            Errno::result(unsafe {
+                libc::setsockopt(
+                    fd.as_raw_fd(),
+                    level,
+                    name,
+                    &enable as *const libc::c_int as *const libc::c_void,
+                    mem::size_of_val(&enable) as libc::socklen_t,
+                )
+            })?;
+        }
+        Ok(IcmpSocket {
+            fd,
+            version,
+            datagram,
+        })
+    }
+
+    /// The identifier to match the replies with. The kernel replaces the
+    /// identifier of the requests sent on a ping socket and only passes on
+    /// the replies with its own, so it's None for them.
+    pub fn reply_identifier(&self, identifier: u16) -> Option<u16> {
+        if self.datagram {
+            None
+        } else {
+            Some(identifier)
+        }
+    }
+
+    /// Send message to addr, trying again if a signal interrupts it
+    pub fn send_to(&self, message: &[u8], addr: SocketAddr) -> nix::Result<usize> {
+        let addr = SockaddrStorage::from(addr);
+        loop {
+            match sendto(self.fd.as_raw_fd(), message, &addr, MsgFlags::empty()) {
+                Err(Errno::EINTR) => continue,
+                result => return result,
+            }
+        }
+    }
+
+    /// Make recv fail with EAGAIN after waiting for timeout
+    pub fn set_timeout(&self, timeout: Duration) -> nix::Result<()> {
+        // A zero timeout would block forever
+        let timeout = timeout.max(Duration::from_micros(1));
+        setsockopt(
+            &self.fd,
+            ReceiveTimeout,
+            &TimeVal::microseconds(timeout.as_micros() as i64),
+        )
+    }
+
+    /// Receive a packet into buf, reading the TTL from the IPv4 header or
+    /// the control message. Returns None for a packet too short to hold
+    /// an IPv4 header.
+    pub fn recv<'a>(&self, buf: &'a mut [u8]) -> nix::Result<Option<Packet<'a>>> {
+        let (size, source, ttl) = self.recvmsg(buf)?;
+        let packet = &buf[..size];
+        let (ttl, message) = if self.datagram || self.version == IpVersion::V6 {
+            (ttl, packet)
+        } else {
+            match icmp::strip_ipv4_header(packet) {
+                Some((ttl, message)) => (Some(ttl), message),
+                None => return Ok(None),
+            }
+        };
+        Ok(Some(Packet {
+            message,
+            source,
+            ttl,
+        }))
+    }
+
+    /// recvmsg(2) returning the size, source address and the TTL or hop
+    /// limit control message, which nix can't parse
+    fn recvmsg(&self, buf: &mut [u8]) -> nix::Result<(usize, Option<SocketAddr>, Option<u8>)> {
+        // Safe because all zeroes is a valid sockaddr_storage and msghdr
+        let mut addr: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        // u64s to align the control messages
+        let mut control = [0u64; 8];
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = &mut addr as *mut libc::sockaddr_storage as *mut libc::c_void;
+        msg.msg_namelen = mem::size_of_val(&addr) as libc::socklen_t;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = mem::size_of_val(&control) as _;
+
+        // Safe because msg points to buffers of the sizes it gives
+        let size = Errno::result(unsafe { libc::recvmsg(self.fd.as_raw_fd(), &mut msg, 0) })?;
+
+        let mut ttl = None;
+        // Safe because recvmsg filled in msg_control up to msg_controllen
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+            while !cmsg.is_null() {
+                let level_type = ((*cmsg).cmsg_level, (*cmsg).cmsg_type);
+                if level_type == (libc::IPPROTO_IP, libc::IP_TTL)
+                    || level_type == (libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT)
+                {
+                    let value = (libc::CMSG_DATA(cmsg) as *const libc::c_int).read_unaligned();
+                    ttl = Some(value as u8);
+                }
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+        }
+
+        // Safe because addr holds the address recvmsg filled in
+        let source = unsafe {
+            SockaddrStorage::from_raw(
+                &addr as *const libc::sockaddr_storage as *const libc::sockaddr,
+                Some(msg.msg_namelen),
+            )
+        }
+        .and_then(
+            |source| match (source.as_sockaddr_in(), source.as_sockaddr_in6()) {
+                (Some(v4), _) => Some(SocketAddr::V4(SocketAddrV4::from(*v4))),
+                (_, Some(v6)) => Some(SocketAddr::V6(SocketAddrV6::from(*v6))),
+                _ => None,
+            },
+        );
+        Ok((size as usize, source, ttl))
+    }
+}
@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+/// Counts of the packets and their round-trip times, in milliseconds
+#[derive(Default)]
+pub struct Statistics {
+    transmitted: u32,
+    received: u32,
+    rtt_min: f64,
+    rtt_max: f64,
+    rtt_sum: f64,
+    rtt_sum_squares: f64,
+    /// Moving average weighting the last reply by 1/8, like TCP's
+    /// smoothed round-trip time
+    rtt_ewma: f64,
+}
+
+impl Statistics {
+    pub fn transmitted(&self) -> u32 {
+        self.transmitted
+    }
+
+    pub fn received(&self) -> u32 {
+        self.received
+    }
+
+    pub fn add_transmitted(&mut self) {
+        self.transmitted += 1;
+    }
+
+    pub fn add_received(&mut self, rtt: Duration) {
+        let rtt = rtt.as_secs_f64() * 1000.0;
+        if self.received == 0 {
+            self.rtt_min = rtt;
+            self.rtt_max = rtt;
+            self.rtt_ewma = rtt;
+        } else {
+            self.rtt_min = self.rtt_min.min(rtt);
+            self.rtt_max = self.rtt_max.max(rtt);
+            self.rtt_ewma += (rtt - self.rtt_ewma) / 8.0;
+        }
+        self.rtt_sum += rtt;
+        self.rtt_sum_squares += rtt * rtt;
+        self.received += 1;
+    }
+
+    /// Percentage of the transmitted packets without a reply
+    fn loss(&self) -> f64 {
+        if self.transmitted == 0 {
+            return 0.0;
+        }
+        f64::from(self.transmitted - self.received) * 100.0 / f64::from(self.transmitted)
+    }
+
+    fn rtt_avg(&self) -> f64 {
+        self.rtt_sum / f64::from(self.received)
+    }
+
+    /// Standard deviation of the round-trip times
+    fn rtt_mdev(&self) -> f64 {
+        let avg = self.rtt_avg();
+        (self.rtt_sum_squares / f64::from(self.received) - avg * avg)
+            .max(0.0)
+            .sqrt()
+    }
+
+    /// The summary printed when ping finishes:
+    ///
+    /// --- 127.0.0.1 ping statistics ---
+    /// 3 packets transmitted, 3 received, 0% packet loss, time 2003ms
+    /// rtt min/avg/max/mdev = 0.045/0.062/0.080/0.014 ms
+    pub fn summary(&self, host: &str, elapsed: Duration) -> String {
+        let mut summary = format!(
+            "--- {} ping statistics ---\n\
+             {} packets transmitted, {} received, {}% packet loss, time {}ms\n",
+            host,
+            self.transmitted,
+            self.received,
+            format_percentage(self.loss()),
+            elapsed.as_millis()
+        );
+        if self.received > 0 {
+            summary += &format!(
+                "rtt min/avg/max/mdev = {:.3}/{:.3}/{:.3}/{:.3} ms\n",
+                self.rtt_min,
+                self.rtt_avg(),
+                self.rtt_max,
+                self.rtt_mdev()
+            );
+        }
+        summary
+    }
+
+    /// The status line printed on SIGQUIT while pinging:
+    ///
+    /// 3/3 packets, 0% loss, min/avg/ewma/max = 0.045/0.062/0.058/0.080 ms
+    pub fn interim(&self) -> String {
+        let mut interim = format!(
+            "{}/{} packets, {}% loss",
+            self.received,
+            self.transmitted,
+            format_percentage(self.loss())
+        );
+        if self.received > 0 {
+            interim += &format!(
+                ", min/avg/ewma/max = {:.3}/{:.3}/{:.3}/{:.3} ms",
+                self.rtt_min,
+                self.rtt_avg(),
+                self.rtt_ewma,
+                self.rtt_max
+            );
+        }
+        interim
+    }
+}
+
+/// Format a percentage with up to 6 significant digits and no trailing
+/// zeros, like printf's %g in iputils: 0, 25, 33.3333
+fn format_percentage(percentage: f64) -> String {
+    let integer_digits = (percentage.abs().max(1.0).log10().floor() as usize) + 1;
+    let decimals = 6usize.saturating_sub(integer_digits);
+    let formatted = format!("{:.*}", decimals, percentage);
+    if formatted.contains('.') {
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        formatted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statistics(transmitted: u32, rtts_ms: &[u64]) -> Statistics {
+        let mut stats = Statistics::default();
+        for _ in 0..transmitted {
+            stats.add_transmitted();
+        }
+        for rtt in rtts_ms {
+            stats.add_received(Duration::from_millis(*rtt));
+        }
+        stats
+    }
+
+    /// Verify percentages are formatted like printf's %g
+    #[test]
+    fn test_format_percentage() {
+        assert_eq!(format_percentage(0.0), "0");
+        assert_eq!(format_percentage(25.0), "25");
+        assert_eq!(format_percentage(100.0), "100");
+        assert_eq!(format_percentage(100.0 / 3.0), "33.3333");
+        assert_eq!(format_percentage(200.0 / 3.0), "66.6667");
+        assert_eq!(format_percentage(0.5), "0.5");
+        assert_eq!(format_percentage(100.0 / 7.0), "14.2857");
+    }
+
+    /// Verify the loss, including before anything was transmitted
+    #[test]
+    fn test_loss() {
+        assert_eq!(statistics(0, &[]).loss(), 0.0);
+        assert_eq!(statistics(4, &[]).loss(), 100.0);
+        assert_eq!(statistics(4, &[1, 2, 3]).loss(), 25.0);
+        assert_eq!(
+            statistics(3, &[1, 2]).interim(),
+            "2/3 packets, 33.3333% loss, min/avg/ewma/max = 1.000/1.500/1.125/2.000 ms"
+        );
+    }
+
+    /// Verify the round-trip times against values worked out by hand
+    #[test]
+    fn test_rtt() {
+        // The mean of 1, 2, 3 and 4 is 2.5 and the mean of their squares
+        // 7.5, so the deviation is sqrt(7.5 - 2.5²) = sqrt(1.25) = 1.118
+        let stats = statistics(4, &[1, 2, 3, 4]);
+        assert_eq!(stats.rtt_avg(), 2.5);
+        assert!((stats.rtt_mdev() - 1.25f64.sqrt()).abs() < 1e-9);
+        assert_eq!(
+            stats.summary("host", Duration::from_millis(3004)),
+            "--- host ping statistics ---\n\
+             4 packets transmitted, 4 received, 0% packet loss, time 3004ms\n\
+             rtt min/avg/max/mdev = 1.000/2.500/4.000/1.118 ms\n"
+        );
+        // Equal times don't deviate, whatever the rounding
+        assert_eq!(statistics(3, &[7, 7, 7]).rtt_mdev(), 0.0);
+    }
+
+    /// Verify there are no round-trip times when nothing was received
+    #[test]
+    fn test_nothing_received() {
+        let stats = statistics(2, &[]);
+        assert_eq!(
+            stats.summary("host", Duration::from_millis(1001)),
+            "--- host ping statistics ---\n\
+             2 packets transmitted, 0 received, 100% packet loss, time 1001ms\n"
+        );
+        assert_eq!(stats.interim(), "0/2 packets, 100% loss");
+        assert_eq!(
+            statistics(0, &[]).summary("host", Duration::ZERO),
+            "--- host ping statistics ---\n\
+             0 packets transmitted, 0 received, 0% packet loss, time 0ms\n"
+        );
+    }
+}
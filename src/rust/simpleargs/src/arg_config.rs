@@ -0,0 +1,316 @@
+use std::ops::{Bound, RangeBounds};
+
+use super::{matches::DeclaredType, range::ArgRange, utils, Arg, ArgType};
+
+/// What the parser does each time a flag is passed
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Action {
+    /// Store the value, overwriting any earlier occurrence
+    Set,
+    /// Collect the value of every occurrence into an Arg::List. A positional
+    /// argument with Append is variadic and takes all the remaining values.
+    Append,
+    /// Count how many times a boolean flag is passed, e.g. 3 for "-vvv".
+    /// The count is stored as an Arg::Unsigned64.
+    Count,
+}
+
+/// What kind of value an argument takes, used by shell completions
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ValueHint {
+    /// Anything, nothing is suggested
+    Other,
+    /// A path to a file
+    FilePath,
+    /// A path to a directory
+    DirPath,
+}
+
+/// Configuration of a single flag or positional argument.
+///
+/// If no short_flag or long_flag then this is a positional argument.
+/// If no arg_type for short_flag or long_flag then this is boolean arg_type.
+///
+/// ```
+/// use simpleargs::{Action, ArgConfig, ArgType};
+///
+/// let include = ArgConfig::new("include".to_string())
+///     .short_flag('I')
+///     .arg_type(ArgType::String)
+///     .action(Action::Append)
+///     .description("Add a directory to the search path".to_string());
+/// ```
+///
+/// A positional argument with Action::Append is variadic. Its min and max
+/// occurrences bound how many values it takes, e.g. "cp SRC... DST":
+///
+/// ```
+/// use simpleargs::{Action, ArgConfig, ArgType};
+///
+/// let src = ArgConfig::new("src".to_string())
+///     .arg_type(ArgType::String)
+///     .action(Action::Append)
+///     .min_occurrences(1);
+/// let dst = ArgConfig::new("dst".to_string())
+///     .arg_type(ArgType::String)
+///     .required(true);
+/// ```
+pub struct ArgConfig {
+    pub(crate) name: String,
+    pub(crate) short_flag: Option<char>,
+    pub(crate) long_flag: Option<String>,
+    pub(crate) required: bool,
+    pub(crate) arg_type: Option<ArgType>,
+    pub(crate) description: String,
+    /// Global flags are accepted by the parser and all of its subcommands
+    pub(crate) global: bool,
+    pub(crate) action: Action,
+    /// Split each value of an Append flag on this character
+    pub(crate) delimiter: Option<char>,
+    /// How many times an Append flag must be passed, or how many values
+    /// a variadic positional must take
+    pub(crate) min_occurrences: usize,
+    /// How many times an Append flag may be passed, or how many values
+    /// a variadic positional may take. None is unlimited.
+    pub(crate) max_occurrences: Option<usize>,
+    /// Value used when the arg isn't passed and its env var isn't set
+    pub(crate) default_value: Option<Arg>,
+    /// Environment variable read when the arg isn't passed
+    pub(crate) env: Option<String>,
+    /// The allowed values of a numeric arg
+    pub(crate) range: Option<ArgRange>,
+    /// What shell completions suggest for the value
+    pub(crate) value_hint: ValueHint,
+    /// Accept values starting with '-' after the flag, e.g. "--pattern -x"
+    pub(crate) allow_hyphen_values: bool,
+    /// Heading the arg is listed under in the help screen
+    pub(crate) heading: Option<String>,
+    /// Placeholder of the value in the help screen, e.g. "FILE"
+    pub(crate) value_name: Option<String>,
+    /// Also accept "--no-<long_flag>", which sets the flag to false
+    pub(crate) negatable: bool,
+    /// Names of the args or groups that must be passed along with this one
+    pub(crate) requires: Vec<String>,
+    /// Names of the args or groups that can't be passed along with this one
+    pub(crate) conflicts_with: Vec<String>,
+}
+
+impl ArgConfig {
+    /// Start the configuration of a flag or positional argument. name is
+    /// the key its value is retrieved with after parsing.
+    pub fn new(name: String) -> Self {
+        ArgConfig {
+            name,
+            short_flag: None,
+            long_flag: None,
+            required: false,
+            arg_type: None,
+            description: "".to_string(),
+            global: false,
+            action: Action::Set,
+            delimiter: None,
+            min_occurrences: 0,
+            max_occurrences: None,
+            default_value: None,
+            env: None,
+            range: None,
+            value_hint: ValueHint::Other,
+            allow_hyphen_values: false,
+            heading: None,
+            value_name: None,
+            negatable: false,
+            requires: vec![],
+            conflicts_with: vec![],
+        }
+    }
+
+    /// Accept the flag as "--long_flag"
+    pub fn long_flag(mut self, long_flag: String) -> Self {
+        self.long_flag = Some(long_flag);
+        self
+    }
+
+    /// Accept the flag as "-c"
+    pub fn short_flag(mut self, short_flag: char) -> Self {
+        self.short_flag = Some(short_flag);
+        self
+    }
+
+    /// Fail parsing if the argument isn't passed
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// The type of the flag's value. Flags without one are booleans.
+    pub fn arg_type(mut self, arg_type: ArgType) -> Self {
+        self.arg_type = Some(arg_type);
+        self
+    }
+
+    /// Description shown in the help screen
+    pub fn description(mut self, description: String) -> Self {
+        self.description = description;
+        self
+    }
+
+    /// Accept the flag in this parser and all of its subcommands
+    pub fn global(mut self, global: bool) -> Self {
+        self.global = global;
+        self
+    }
+
+    /// What to do each time the flag is passed
+    pub fn action(mut self, action: Action) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Split each value of an Append flag on delimiter,
+    /// e.g. "--tags=a,b,c" collects ["a", "b", "c"] with ','
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    /// How many times an Append flag must be passed (inclusive), or the
+    /// minimum number of values of a variadic positional
+    pub fn min_occurrences(mut self, min_occurrences: usize) -> Self {
+        self.min_occurrences = min_occurrences;
+        self
+    }
+
+    /// How many times an Append flag may be passed (inclusive), or the
+    /// maximum number of values of a variadic positional
+    pub fn max_occurrences(mut self, max_occurrences: usize) -> Self {
+        self.max_occurrences = Some(max_occurrences);
+        self
+    }
+
+    /// Value used when the arg is neither passed nor set in its environment
+    /// variable. It should be of the Arg variant matching arg_type.
+    pub fn default_value(mut self, default_value: Arg) -> Self {
+        self.default_value = Some(default_value);
+        self
+    }
+
+    /// Read the value from the environment variable env when the arg isn't
+    /// passed. The value is parsed the same way as on the command line.
+    pub fn env(mut self, env: String) -> Self {
+        self.env = Some(env);
+        self
+    }
+
+    /// Only accept numeric values within range, e.g. 1..=65535 or 0.0..1.0.
+    /// The bounds may be of any numeric type, regardless of arg_type.
+    pub fn range<T: Into<Arg> + Clone>(mut self, range: impl RangeBounds<T>) -> Self {
+        let to_arg = |bound: Bound<&T>| match bound {
+            Bound::Included(val) => Bound::Included(val.clone().into()),
+            Bound::Excluded(val) => Bound::Excluded(val.clone().into()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        self.range = Some(ArgRange {
+            start: to_arg(range.start_bound()),
+            end: to_arg(range.end_bound()),
+        });
+        self
+    }
+
+    /// What shell completions suggest for the value, e.g. file names
+    pub fn value_hint(mut self, value_hint: ValueHint) -> Self {
+        self.value_hint = value_hint;
+        self
+    }
+
+    /// Accept a value starting with '-' in the argument after the flag, e.g.
+    /// "--pattern -x". Otherwise such a value is rejected as a forgotten
+    /// value, unless it is a negative number for a numeric arg_type or is
+    /// attached to the flag as in "--pattern=-x".
+    pub fn allow_hyphen_values(mut self, allow_hyphen_values: bool) -> Self {
+        self.allow_hyphen_values = allow_hyphen_values;
+        self
+    }
+
+    /// List the arg under heading in the help screen instead of "Options"
+    /// or "Arguments", grouping it with the other args with that heading
+    pub fn heading(mut self, heading: String) -> Self {
+        self.heading = Some(heading);
+        self
+    }
+
+    /// Show the value as <value_name> in the help screen instead of <name>
+    pub fn value_name(mut self, value_name: String) -> Self {
+        self.value_name = Some(value_name);
+        self
+    }
+
+    /// Also accept the boolean flag as "--no-<long_flag>", which sets it to
+    /// false. The last of "--color" and "--no-color" wins.
+    pub fn negatable(mut self, negatable: bool) -> Self {
+        self.negatable = negatable;
+        self
+    }
+
+    /// Fail parsing if this arg is passed without the arg or group called
+    /// name, e.g. "--user" requires "password". Can be called repeatedly.
+    pub fn requires(mut self, name: String) -> Self {
+        self.requires.push(name);
+        self
+    }
+
+    /// Fail parsing if this arg is passed along with the arg or group
+    /// called name, e.g. "--json" conflicts with "quiet". Can be called
+    /// repeatedly.
+    pub fn conflicts_with(mut self, name: String) -> Self {
+        self.conflicts_with.push(name);
+        self
+    }
+
+    /// Whether this is a positional argument rather than a flag
+    pub(crate) fn is_positional(&self) -> bool {
+        self.long_flag.is_none() && self.short_flag.is_none()
+    }
+
+    /// The flag setting a negatable flag to false, e.g. "--no-color"
+    pub(crate) fn negated_flag(&self) -> Option<String> {
+        match &self.long_flag {
+            Some(long_flag) if self.negatable => Some(format!("--no-{}", long_flag)),
+            _ => None,
+        }
+    }
+
+    /// How the value is shown in usage lines, e.g. "<count>"
+    pub(crate) fn value_placeholder(&self) -> String {
+        format!("<{}>", self.value_name.as_ref().unwrap_or(&self.name))
+    }
+
+    /// The type of the values the arg takes: its arg_type, or Boolean for
+    /// a flag without a value and Unsigned64 for a counted flag
+    pub(crate) fn declared_type(&self) -> DeclaredType {
+        match (&self.arg_type, self.action) {
+            (Some(arg_type), _) => DeclaredType {
+                name: arg_type.value_parser().type_name(),
+                sample: arg_type.sample_value(),
+            },
+            (None, Action::Count) => DeclaredType {
+                name: "Unsigned64".to_string(),
+                sample: Some(Arg::Unsigned64(0)),
+            },
+            (None, _) => DeclaredType {
+                name: "Boolean".to_string(),
+                sample: Some(Arg::Boolean(false)),
+            },
+        }
+    }
+
+    /// How the arg is referred to in messages: its flag, or <name> if positional
+    pub(crate) fn display_name(&self) -> String {
+        if let Some(long_flag) = &self.long_flag {
+            utils::add_dashes_to_long_flag(long_flag)
+        } else if let Some(short_flag) = self.short_flag {
+            utils::add_dash_to_short_flag(short_flag)
+        } else {
+            format!("<{}>", self.name)
+        }
+    }
+}
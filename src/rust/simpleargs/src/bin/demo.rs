@@ -1,6 +1,6 @@
-use simpleargs::{ArgType, Parser};
+use simpleargs::{ArgType, Matches};
 
-fn parse_args() -> Parser {
+fn parse_args() -> Matches {
     simpleargs::new("test parser".to_string())
         .add_flag(
             "verbose".to_string(),
@@ -34,15 +34,18 @@ fn parse_args() -> Parser {
             Some(ArgType::String),
             "Test positional argument".to_string(),
         )
+        .completions_flag(true)
         .parse(std::env::args())
 }
 
-fn main() {
+fn main() -> Result<(), simpleargs::Error> {
     let p = parse_args();
 
     println!("Demo of simpleargs usage");
-    println!("verbose: {:?}", p.get_arg("verbose"));
-    println!("myflag: {:?}", p.get_arg("myflag"));
-    println!("optionalFlag: {:?}", p.get_arg("optionalFlag"));
-    println!("posArg: {:?}", p.get_arg("posArg"));
+    println!("verbose: {:?}", p.get::<bool>("verbose")?);
+    println!("myflag: {:?}", p.get::<String>("myflag")?);
+    println!("optionalFlag: {:?}", p.get::<i32>("optionalFlag")?);
+    println!("posArg: {:?}", p.get::<String>("posArg")?);
+
+    Ok(())
 }
@@ -0,0 +1,508 @@
+use std::{fmt, rc::Rc, str::FromStr};
+
+use super::{Action, ArgConfig, Parser, ValueHint};
+
+/// A shell that completion scripts can be generated for
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err("expected one of bash, zsh or fish".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Shell::Bash => write!(f, "bash"),
+            Shell::Zsh => write!(f, "zsh"),
+            Shell::Fish => write!(f, "fish"),
+        }
+    }
+}
+
+/// What to suggest for the value of a flag or positional argument
+enum ValueCompletion {
+    /// A boolean flag, it has no value
+    NoValue,
+    /// A value nothing can be suggested for
+    Any,
+    Files,
+    Dirs,
+    Choices(Vec<String>),
+}
+
+impl ValueCompletion {
+    fn of(config: &ArgConfig) -> Self {
+        let arg_type = match &config.arg_type {
+            Some(arg_type) => arg_type,
+            None => return ValueCompletion::NoValue,
+        };
+
+        match config.value_hint {
+            ValueHint::FilePath => ValueCompletion::Files,
+            ValueHint::DirPath => ValueCompletion::Dirs,
+            ValueHint::Other => {
+                let choices = arg_type.value_parser().possible_values();
+                if choices.is_empty() {
+                    ValueCompletion::Any
+                } else {
+                    ValueCompletion::Choices(choices)
+                }
+            }
+        }
+    }
+}
+
+/// A parser and the subcommand names leading to it, with the global flags
+/// of its parents
+pub(crate) struct Level<'a> {
+    pub(crate) path: Vec<&'a str>,
+    pub(crate) parser: &'a Parser,
+    pub(crate) flags: Vec<Rc<ArgConfig>>,
+}
+
+impl Level<'_> {
+    /// Function or state name of the level, e.g. "tool__remote__add"
+    fn id(&self, bin_name: &str) -> String {
+        let mut id = identifier(bin_name);
+        for name in &self.path {
+            id.push_str("__");
+            id.push_str(&identifier(name));
+        }
+        id
+    }
+
+    /// Function or state name of the subcommand name of this level
+    fn subcommand_id(&self, bin_name: &str, name: &str) -> String {
+        format!("{}__{}", self.id(bin_name), identifier(name))
+    }
+}
+
+/// Replace characters that can't appear in a shell function name
+fn identifier(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Quote value for the shell inside single quotes
+fn single_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Every level of subcommands, parents before their children
+pub(crate) fn levels(parser: &Parser) -> Vec<Level<'_>> {
+    let mut levels = vec![];
+    collect_levels(parser, vec![], vec![], &mut levels);
+    levels
+}
+
+fn collect_levels<'a>(
+    parser: &'a Parser,
+    path: Vec<&'a str>,
+    globals: Vec<Rc<ArgConfig>>,
+    levels: &mut Vec<Level<'a>>,
+) {
+    let mut flags = globals.clone();
+    flags.extend(parser.flag_configs.iter().cloned());
+
+    let mut sub_globals = globals;
+    sub_globals.extend(
+        parser
+            .flag_configs
+            .iter()
+            .filter(|flag| flag.global)
+            .cloned(),
+    );
+
+    levels.push(Level {
+        path: path.clone(),
+        parser,
+        flags,
+    });
+
+    for subcommand in &parser.subcommands {
+        let mut sub_path = path.clone();
+        sub_path.push(&subcommand.command);
+        collect_levels(subcommand, sub_path, sub_globals.clone(), levels);
+    }
+}
+
+impl Parser {
+    /// Generate a script that makes shell complete the flags, subcommands
+    /// and values of bin_name, the name the program is installed as.
+    ///
+    /// ```
+    /// use simpleargs::Shell;
+    ///
+    /// let parser = simpleargs::new("example".to_string()).add_flag(
+    ///     "verbose".to_string(),
+    ///     Some("verbose".to_string()),
+    ///     Some('v'),
+    ///     false,
+    ///     None,
+    ///     "Print more output".to_string(),
+    /// );
+    /// let script = parser.completions(Shell::Bash, "example");
+    /// assert!(script.contains("complete -F _example example"));
+    /// ```
+    pub fn completions(&self, shell: Shell, bin_name: &str) -> String {
+        match shell {
+            Shell::Bash => self.bash_completions(bin_name),
+            Shell::Zsh => self.zsh_completions(bin_name),
+            Shell::Fish => self.fish_completions(bin_name),
+        }
+    }
+
+    /// Accept the hidden flag "--generate-completions <shell>", which prints
+    /// the completion script for shell and exits. Parser::try_parse returns
+    /// it as an ErrorKind::CompletionsRequested error holding the script.
+    pub fn completions_flag(mut self, enabled: bool) -> Self {
+        self.completions_flag = enabled;
+        self
+    }
+
+    fn bash_completions(&self, bin_name: &str) -> String {
+        let levels = levels(self);
+        let mut script = format!("_{}() {{\n", identifier(bin_name));
+        script.push_str("    local cur prev cmd opts i\n");
+        script.push_str("    cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+        script.push_str("    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+        script.push_str(&format!("    cmd=\"{}\"\n\n", levels[0].id(bin_name)));
+
+        // Find the subcommand being completed from the words before the cursor
+        script.push_str("    for ((i = 1; i < COMP_CWORD; i++)); do\n");
+        script.push_str("        case \"${cmd},${COMP_WORDS[i]}\" in\n");
+        for level in &levels {
+            for subcommand in &level.parser.subcommands {
+                script.push_str(&format!(
+                    "            {})\n                cmd={}\n                ;;\n",
+                    single_quote(&format!("{},{}", level.id(bin_name), subcommand.command)),
+                    level.subcommand_id(bin_name, &subcommand.command)
+                ));
+            }
+        }
+        script.push_str("        esac\n    done\n\n");
+
+        script.push_str("    case \"${cmd}\" in\n");
+        for level in &levels {
+            let mut opts = vec!["--help".to_string()];
+            let mut value_cases = String::new();
+            for flag in &level.flags {
+                let mut names = vec![];
+                if let Some(long_flag) = &flag.long_flag {
+                    names.push(format!("--{}", long_flag));
+                }
+                if let Some(short_flag) = flag.short_flag {
+                    names.push(format!("-{}", short_flag));
+                }
+                opts.extend(names.iter().cloned());
+                opts.extend(flag.negated_flag());
+
+                let compgen = match ValueCompletion::of(flag) {
+                    ValueCompletion::NoValue => continue,
+                    ValueCompletion::Any => "".to_string(),
+                    ValueCompletion::Files => {
+                        "COMPREPLY=($(compgen -f -- \"${cur}\"))\n                    ".to_string()
+                    }
+                    ValueCompletion::Dirs => {
+                        "COMPREPLY=($(compgen -d -- \"${cur}\"))\n                    ".to_string()
+                    }
+                    ValueCompletion::Choices(choices) => format!(
+                        "COMPREPLY=($(compgen -W {} -- \"${{cur}}\"))\n                    ",
+                        single_quote(&choices.join(" "))
+                    ),
+                };
+                value_cases.push_str(&format!(
+                    "                {})\n                    {}return 0\n                    ;;\n",
+                    names.join("|"),
+                    compgen
+                ));
+            }
+            for subcommand in &level.parser.subcommands {
+                opts.push(subcommand.command.clone());
+            }
+
+            // Suggest the values of the positional arguments too
+            let mut positionals = String::new();
+            for arg in &level.parser.pos_arg_configs {
+                match ValueCompletion::of(arg) {
+                    ValueCompletion::Files => {
+                        positionals.push_str(" $(compgen -f -- \"${cur}\")");
+                    }
+                    ValueCompletion::Dirs => {
+                        positionals.push_str(" $(compgen -d -- \"${cur}\")");
+                    }
+                    ValueCompletion::Choices(choices) => {
+                        opts.extend(choices);
+                    }
+                    ValueCompletion::NoValue | ValueCompletion::Any => {}
+                }
+            }
+
+            script.push_str(&format!("        {})\n", level.id(bin_name)));
+            if !value_cases.is_empty() {
+                script.push_str("            case \"${prev}\" in\n");
+                script.push_str(&value_cases);
+                script.push_str("            esac\n");
+            }
+            script.push_str(&format!(
+                "            opts={}\n",
+                single_quote(&opts.join(" "))
+            ));
+            script.push_str(&format!(
+                "            COMPREPLY=($(compgen -W \"${{opts}}\" -- \"${{cur}}\"){})\n",
+                positionals
+            ));
+            script.push_str("            ;;\n");
+        }
+        script.push_str("    esac\n}\n\n");
+
+        script.push_str(&format!(
+            "complete -F _{} {}\n",
+            identifier(bin_name),
+            bin_name
+        ));
+        script
+    }
+
+    fn zsh_completions(&self, bin_name: &str) -> String {
+        let levels = levels(self);
+        let mut script = format!("#compdef {}\n", bin_name);
+
+        for level in &levels {
+            script.push_str(&format!("\n_{}() {{\n", level.id(bin_name)));
+            script.push_str("    local context state state_descr line\n");
+            script.push_str("    _arguments -C \\\n");
+            script.push_str("        '--help[Print this help page]' \\\n");
+
+            for flag in &level.flags {
+                let completion = ValueCompletion::of(flag);
+                let takes_value = !matches!(completion, ValueCompletion::NoValue);
+                let mut names = vec![];
+                if let Some(short_flag) = flag.short_flag {
+                    let suffix = if takes_value { "+" } else { "" };
+                    names.push(format!("-{}{}", short_flag, suffix));
+                }
+                if let Some(long_flag) = &flag.long_flag {
+                    let suffix = if takes_value { "=" } else { "" };
+                    names.push(format!("--{}{}", long_flag, suffix));
+                }
+                names.extend(flag.negated_flag());
+
+                // Append and Count flags may be repeated, other flags
+                // exclude themselves
+                let prefix = if flag.action != Action::Set {
+                    "'*'".to_string()
+                } else {
+                    let exclusions: Vec<&str> = names
+                        .iter()
+                        .map(|name| name.trim_end_matches(['+', '=']))
+                        .collect();
+                    format!("'({})'", exclusions.join(" "))
+                };
+                let names = if names.len() > 1 {
+                    format!("{{{}}}", names.join(","))
+                } else {
+                    names.join("")
+                };
+
+                let mut spec = format!("[{}]", zsh_escape(&flag.description));
+                if takes_value {
+                    spec.push_str(&format!(":{}:{}", flag.name, zsh_action(&completion)));
+                }
+                script.push_str(&format!(
+                    "        {}{}{} \\\n",
+                    prefix,
+                    names,
+                    single_quote(&spec)
+                ));
+            }
+
+            if level.parser.subcommands.is_empty() {
+                for arg in &level.parser.pos_arg_configs {
+                    let repeat = if arg.action == Action::Append {
+                        "*"
+                    } else {
+                        ""
+                    };
+                    let optional = if arg.required || arg.min_occurrences > 0 {
+                        ""
+                    } else {
+                        ":"
+                    };
+                    let spec = format!(
+                        "{}:{}{}:{}",
+                        repeat,
+                        optional,
+                        zsh_escape(&arg.name),
+                        zsh_action(&ValueCompletion::of(arg))
+                    );
+                    script.push_str(&format!("        {} \\\n", single_quote(&spec)));
+                }
+            } else {
+                // The first positional argument names the subcommand and
+                // the rest of the words are completed by it
+                let subcommands: Vec<String> = level
+                    .parser
+                    .subcommands
+                    .iter()
+                    .map(|sub| {
+                        format!(
+                            "{}\\:\"{}\"",
+                            sub.command,
+                            zsh_escape(&sub.description).replace('"', "\\\"")
+                        )
+                    })
+                    .collect();
+                script.push_str(&format!(
+                    "        {} \\\n",
+                    single_quote(&format!(":subcommand:(({}))", subcommands.join(" ")))
+                ));
+                script.push_str("        '*:: :->subcommand' \\\n");
+            }
+            script.push_str("        && return 0\n");
+
+            if !level.parser.subcommands.is_empty() {
+                script.push_str("\n    case $state in\n        subcommand)\n");
+                script.push_str("            case $words[1] in\n");
+                for subcommand in &level.parser.subcommands {
+                    script.push_str(&format!(
+                        "                {})\n                    _{}\n                    ;;\n",
+                        single_quote(&subcommand.command),
+                        level.subcommand_id(bin_name, &subcommand.command)
+                    ));
+                }
+                script.push_str("            esac\n            ;;\n    esac\n");
+            }
+            script.push_str("}\n");
+        }
+
+        // Works both autoloaded from $fpath and sourced directly
+        let id = levels[0].id(bin_name);
+        script.push_str(&format!(
+            "\nif [ \"$funcstack[1]\" = \"_{id}\" ]; then\n    _{id} \"$@\"\nelse\n    compdef _{id} {bin_name}\nfi\n"
+        ));
+        script
+    }
+
+    fn fish_completions(&self, bin_name: &str) -> String {
+        let levels = levels(self);
+        let mut script = String::new();
+
+        for level in &levels {
+            let names: Vec<&str> = level
+                .parser
+                .subcommands
+                .iter()
+                .map(|sub| sub.command.as_str())
+                .collect();
+
+            // Complete this level until one of its subcommands is typed
+            let condition = match level.path.last() {
+                None if names.is_empty() => None,
+                None => Some("__fish_use_subcommand".to_string()),
+                Some(name) if names.is_empty() => {
+                    Some(format!("__fish_seen_subcommand_from {}", name))
+                }
+                Some(name) => Some(format!(
+                    "__fish_seen_subcommand_from {}; and not __fish_seen_subcommand_from {}",
+                    name,
+                    names.join(" ")
+                )),
+            };
+            let complete = match &condition {
+                Some(condition) => {
+                    format!("complete -c {} -n {}", bin_name, single_quote(condition))
+                }
+                None => format!("complete -c {}", bin_name),
+            };
+
+            for subcommand in &level.parser.subcommands {
+                script.push_str(&format!(
+                    "{} -f -a {} -d {}\n",
+                    complete,
+                    single_quote(&subcommand.command),
+                    single_quote(&subcommand.description)
+                ));
+            }
+
+            for flag in &level.flags {
+                let mut line = complete.clone();
+                if let Some(short_flag) = flag.short_flag {
+                    line.push_str(&format!(" -s {}", short_flag));
+                }
+                if let Some(long_flag) = &flag.long_flag {
+                    line.push_str(&format!(" -l {}", long_flag));
+                }
+                match ValueCompletion::of(flag) {
+                    ValueCompletion::NoValue => {}
+                    ValueCompletion::Any => line.push_str(" -x"),
+                    ValueCompletion::Files => line.push_str(" -r -F"),
+                    ValueCompletion::Dirs => {
+                        line.push_str(" -x -a '(__fish_complete_directories)'")
+                    }
+                    ValueCompletion::Choices(choices) => {
+                        line.push_str(&format!(" -x -a {}", single_quote(&choices.join(" "))))
+                    }
+                }
+                line.push_str(&format!(" -d {}\n", single_quote(&flag.description)));
+                script.push_str(&line);
+
+                if let Some(negated_flag) = flag.negated_flag() {
+                    script.push_str(&format!(
+                        "{} -l {} -d {}\n",
+                        complete,
+                        negated_flag.trim_start_matches('-'),
+                        single_quote(&flag.description)
+                    ));
+                }
+            }
+
+            for arg in &level.parser.pos_arg_configs {
+                if let ValueCompletion::Choices(choices) = ValueCompletion::of(arg) {
+                    script.push_str(&format!(
+                        "{} -f -a {} -d {}\n",
+                        complete,
+                        single_quote(&choices.join(" ")),
+                        single_quote(&arg.name)
+                    ));
+                }
+            }
+        }
+
+        script
+    }
+}
+
+/// The _arguments action completing a value
+fn zsh_action(completion: &ValueCompletion) -> String {
+    match completion {
+        ValueCompletion::NoValue | ValueCompletion::Any => " ".to_string(),
+        ValueCompletion::Files => "_files".to_string(),
+        ValueCompletion::Dirs => "_files -/".to_string(),
+        ValueCompletion::Choices(choices) => format!("({})", choices.join(" ")),
+    }
+}
+
+/// Escape the characters _arguments gives a meaning to in descriptions
+fn zsh_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
@@ -0,0 +1,695 @@
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{suggest, utils, Action, Arg, ArgConfig, Error, ErrorKind, Parser, Token};
+
+/// The formats a config file can be written in, told apart by extension
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Format {
+    Toml,
+    Ini,
+    Json,
+}
+
+impl Format {
+    fn of(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "toml" => Some(Format::Toml),
+            "ini" | "cfg" | "conf" => Some(Format::Ini),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A "key = value" read from a config file, before it is matched to a flag
+struct Entry {
+    /// The subcommand names of the section it is in, e.g. ["remote", "add"]
+    section: Vec<String>,
+    key: String,
+    /// An array has any number of values, anything else one
+    values: Vec<String>,
+    line: usize,
+}
+
+/// A section header, e.g. ["remote", "add"] for "[remote.add]"
+struct Section {
+    path: Vec<String>,
+    line: usize,
+}
+
+/// The contents of a config file, whatever its format
+#[derive(Default)]
+struct Document {
+    entries: Vec<Entry>,
+    sections: Vec<Section>,
+}
+
+/// The line and message of a syntax error
+type SyntaxError = (usize, String);
+
+/// The values a config file sets for the flags of a parser, by arg name,
+/// and for the flags of its subcommands
+#[derive(Default)]
+pub(crate) struct ConfigValues {
+    pub(crate) values: HashMap<String, Arg>,
+    pub(crate) subcommands: HashMap<String, ConfigValues>,
+}
+
+/// Reads characters of a config file, keeping track of the line
+struct Scanner {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Scanner {
+    /// Scan text, which starts on line
+    fn new(text: &str, line: usize) -> Self {
+        Scanner {
+            chars: text.chars().collect(),
+            pos: 0,
+            line,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: String) -> SyntaxError {
+        (self.line, message)
+    }
+
+    /// Consume c if it is next
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), SyntaxError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(match self.peek() {
+                Some(found) => format!("expected '{}' but found '{}'", c, found),
+                None => format!("expected '{}'", c),
+            }))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.advance();
+        }
+    }
+
+    /// Whether only whitespace and a "#" comment are left
+    fn at_line_end(&mut self) -> bool {
+        self.skip_whitespace();
+        matches!(self.peek(), None | Some('#'))
+    }
+
+    fn expect_line_end(&mut self) -> Result<(), SyntaxError> {
+        if self.at_line_end() {
+            Ok(())
+        } else {
+            Err(self.error(format!("unexpected '{}'", self.chars[self.pos])))
+        }
+    }
+
+    /// The characters up to the next one matching stop
+    fn take_until(&mut self, stop: impl Fn(char) -> bool) -> String {
+        let mut taken = String::new();
+        while let Some(c) = self.peek().filter(|c| !stop(*c)) {
+            taken.push(c);
+            self.advance();
+        }
+        taken
+    }
+
+    /// The rest of a string whose opening '"' was consumed, with
+    /// backslash escapes as in TOML and JSON
+    fn basic_string(&mut self) -> Result<String, SyntaxError> {
+        let mut string = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(string),
+                Some('\\') => {
+                    let escaped = match self.advance() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let hex: String = (0..4).filter_map(|_| self.advance()).collect();
+                            u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error(format!("invalid escape '\\u{}'", hex)))?
+                        }
+                        Some(c) => return Err(self.error(format!("invalid escape '\\{}'", c))),
+                        None => return Err(self.error("unterminated string".to_string())),
+                    };
+                    string.push(escaped);
+                }
+                // The newline was counted, the string started on the line before
+                Some('\n') => return Err((self.line - 1, "unterminated string".to_string())),
+                None => return Err(self.error("unterminated string".to_string())),
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    /// The rest of a TOML literal string whose opening '\'' was consumed
+    fn literal_string(&mut self) -> Result<String, SyntaxError> {
+        let string = self.take_until(|c| c == '\'' || c == '\n');
+        self.expect('\'')
+            .map_err(|_| self.error("unterminated string".to_string()))?;
+        Ok(string)
+    }
+
+    /// A TOML key: bare as in "dry-run", or quoted
+    fn toml_key(&mut self) -> Result<String, SyntaxError> {
+        self.skip_whitespace();
+        if self.eat('"') {
+            return self.basic_string();
+        }
+        if self.eat('\'') {
+            return self.literal_string();
+        }
+        let key = self.take_until(|c| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        if key.is_empty() {
+            return Err(self.error("expected a key".to_string()));
+        }
+        Ok(key)
+    }
+
+    /// A TOML key with dots, e.g. ["remote", "add"] for "remote.add"
+    fn toml_dotted_key(&mut self) -> Result<Vec<String>, SyntaxError> {
+        let mut keys = vec![self.toml_key()?];
+        self.skip_whitespace();
+        while self.eat('.') {
+            keys.push(self.toml_key()?);
+            self.skip_whitespace();
+        }
+        Ok(keys)
+    }
+
+    /// A TOML string, number or boolean as text
+    fn toml_scalar(&mut self) -> Result<String, SyntaxError> {
+        self.skip_whitespace();
+        if self.eat('"') {
+            if self.eat('"') {
+                if self.peek() == Some('"') {
+                    return Err(self.error("multi-line strings are not supported".to_string()));
+                }
+                return Ok("".to_string());
+            }
+            return self.basic_string();
+        }
+        if self.eat('\'') {
+            return self.literal_string();
+        }
+        match self.peek() {
+            Some('[') => return Err(self.error("nested arrays are not supported".to_string())),
+            Some('{') => return Err(self.error("inline tables are not supported".to_string())),
+            _ => {}
+        }
+
+        let value = self.take_until(|c| c.is_whitespace() || matches!(c, ',' | ']' | '#'));
+        // Underscores separate digits, as in 1_000
+        let number = value.replace('_', "");
+        if value == "true" || value == "false" || utils::is_number(&number) {
+            Ok(number)
+        } else if value.is_empty() {
+            Err(self.error("expected a value".to_string()))
+        } else {
+            Err(self.error(format!("invalid value '{}'", value)))
+        }
+    }
+
+    /// A TOML value: a scalar, or a one-line array of scalars
+    fn toml_value(&mut self) -> Result<Vec<String>, SyntaxError> {
+        self.skip_whitespace();
+        if !self.eat('[') {
+            return Ok(vec![self.toml_scalar()?]);
+        }
+
+        let mut values = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.eat(']') {
+                return Ok(values);
+            }
+            values.push(self.toml_scalar()?);
+            self.skip_whitespace();
+            if !self.eat(',') {
+                self.expect(']')?;
+                return Ok(values);
+            }
+        }
+    }
+
+    /// A JSON string, number or boolean as text, or None for null
+    fn json_scalar(&mut self) -> Result<Option<String>, SyntaxError> {
+        if self.eat('"') {
+            return self.basic_string().map(Some);
+        }
+        let value = self.take_until(|c| c.is_whitespace() || matches!(c, ',' | ']' | '}'));
+        match value.as_str() {
+            "null" => Ok(None),
+            "true" | "false" => Ok(Some(value)),
+            _ if utils::is_number(&value) => Ok(Some(value)),
+            "" => Err(self.error(match self.peek() {
+                Some(c) => format!("expected a value but found '{}'", c),
+                None => "expected a value".to_string(),
+            })),
+            _ => Err(self.error(format!("invalid value '{}'", value))),
+        }
+    }
+
+    /// The rest of a JSON array of plain values whose '[' was consumed
+    fn json_array(&mut self) -> Result<Vec<String>, SyntaxError> {
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.eat(']') {
+            return Ok(values);
+        }
+        loop {
+            self.skip_whitespace();
+            if matches!(self.peek(), Some('[' | '{')) {
+                return Err(self.error("arrays can only hold plain values".to_string()));
+            }
+            values.extend(self.json_scalar()?);
+            self.skip_whitespace();
+            if !self.eat(',') {
+                self.expect(']')?;
+                return Ok(values);
+            }
+        }
+    }
+
+    /// The rest of a JSON object whose '{' was consumed. Nested objects
+    /// are the sections of subcommands.
+    fn json_object(
+        &mut self,
+        section: &[String],
+        document: &mut Document,
+    ) -> Result<(), SyntaxError> {
+        self.skip_whitespace();
+        if self.eat('}') {
+            return Ok(());
+        }
+
+        loop {
+            self.skip_whitespace();
+            let line = self.line;
+            self.expect('"')?;
+            let key = self.basic_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+
+            if self.eat('{') {
+                let mut path = section.to_vec();
+                path.push(key);
+                document.sections.push(Section {
+                    path: path.clone(),
+                    line,
+                });
+                self.json_object(&path, document)?;
+            } else {
+                let values = if self.eat('[') {
+                    Some(self.json_array()?)
+                } else {
+                    // null leaves the flag unset
+                    self.json_scalar()?.map(|value| vec![value])
+                };
+                if let Some(values) = values {
+                    document.entries.push(Entry {
+                        section: section.to_vec(),
+                        key,
+                        values,
+                        line,
+                    });
+                }
+            }
+
+            self.skip_whitespace();
+            if !self.eat(',') {
+                return self.expect('}');
+            }
+        }
+    }
+}
+
+/// Parse the flat subset of TOML: "[section]" tables, "key = value" with
+/// strings, numbers and booleans, and one-line arrays of those
+fn parse_toml(text: &str) -> Result<Document, SyntaxError> {
+    let mut document = Document::default();
+    let mut section = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let mut scanner = Scanner::new(line, i + 1);
+        if scanner.at_line_end() {
+            continue;
+        }
+
+        if scanner.eat('[') {
+            if scanner.peek() == Some('[') {
+                return Err(scanner.error("arrays of tables are not supported".to_string()));
+            }
+            section = scanner.toml_dotted_key()?;
+            scanner.expect(']')?;
+            scanner.expect_line_end()?;
+            document.sections.push(Section {
+                path: section.clone(),
+                line: i + 1,
+            });
+            continue;
+        }
+
+        // "remote.add.name = x" is "name = x" in the [remote.add] section
+        let mut keys = scanner.toml_dotted_key()?;
+        scanner.expect('=')?;
+        let values = scanner.toml_value()?;
+        scanner.expect_line_end()?;
+        // Unwrap is safe because toml_dotted_key returns at least one key
+        let key = keys.pop().unwrap();
+        document.entries.push(Entry {
+            section: section.iter().cloned().chain(keys).collect(),
+            key,
+            values,
+            line: i + 1,
+        });
+    }
+    Ok(document)
+}
+
+/// Parse an INI file: "[section]" headers and "key = value" or
+/// "key: value" lines, with ";" and "#" comment lines. A key given more
+/// than once collects all of its values.
+fn parse_ini(text: &str) -> Result<Document, SyntaxError> {
+    let mut document = Document::default();
+    let mut section: Vec<String> = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with([';', '#']) {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header
+                .strip_suffix(']')
+                .ok_or((line_number, "expected ']'".to_string()))?;
+            section = header
+                .split('.')
+                .map(|name| name.trim().to_string())
+                .collect();
+            if section.iter().any(String::is_empty) {
+                return Err((line_number, format!("invalid section '{}'", header)));
+            }
+            document.sections.push(Section {
+                path: section.clone(),
+                line: line_number,
+            });
+            continue;
+        }
+
+        let separator = line
+            .find(['=', ':'])
+            .ok_or((line_number, "expected 'key = value'".to_string()))?;
+        let key = line[..separator].trim();
+        if key.is_empty() {
+            return Err((line_number, "expected a key before the value".to_string()));
+        }
+        let mut value = line[separator + 1..].trim();
+        for quote in ['"', '\''] {
+            if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+                value = &value[1..value.len() - 1];
+            }
+        }
+
+        let existing = document
+            .entries
+            .iter_mut()
+            .find(|entry| entry.section == section && entry.key == key);
+        match existing {
+            Some(entry) => entry.values.push(value.to_string()),
+            None => document.entries.push(Entry {
+                section: section.clone(),
+                key: key.to_string(),
+                values: vec![value.to_string()],
+                line: line_number,
+            }),
+        }
+    }
+    Ok(document)
+}
+
+/// Parse a JSON object of flag values. Nested objects are the sections
+/// of subcommands, arrays may only hold plain values.
+fn parse_json(text: &str) -> Result<Document, SyntaxError> {
+    let mut document = Document::default();
+    let mut scanner = Scanner::new(text, 1);
+    scanner.skip_whitespace();
+    scanner.expect('{')?;
+    scanner.json_object(&[], &mut document)?;
+    scanner.skip_whitespace();
+    if let Some(c) = scanner.peek() {
+        return Err(scanner.error(format!("unexpected '{}' after the object", c)));
+    }
+    Ok(document)
+}
+
+/// The key a flag is set with in a config file: its long flag without
+/// dashes, or its name if it only has a short flag
+fn config_key(arg: &ArgConfig) -> &str {
+    arg.long_flag.as_deref().unwrap_or(&arg.name)
+}
+
+impl Parser {
+    /// Read flag values from the config file whose path is the value of
+    /// the flag called name, e.g. "--config app.toml". The flag is defined
+    /// as usual with add_arg. If it isn't passed, its environment variable
+    /// and then default_config_file are used.
+    ///
+    /// Files ending in .toml, .ini (or .cfg, .conf) and .json are read.
+    /// Keys are the long flags without dashes, and sections hold the
+    /// flags of subcommands, e.g. "[remote.add]". Values are parsed like
+    /// command line values. The command line and environment variables
+    /// take precedence over the file, and the file over default values.
+    pub fn config_file_flag(mut self, name: String) -> Self {
+        self.config_file_flag = Some(name);
+        self
+    }
+
+    /// Read flag values from the config file at path when no other config
+    /// file is given. Unlike a file given on the command line, it is
+    /// fine for it not to exist.
+    pub fn default_config_file(mut self, path: PathBuf) -> Self {
+        self.default_config_file = Some(path);
+        self
+    }
+
+    /// The path given with the config file flag or its environment variable
+    fn config_file_path(&self, tokens: &[Token]) -> Option<PathBuf> {
+        let name = self.config_file_flag.as_ref()?;
+        let flag = self.flag_configs.iter().find(|flag| &flag.name == name)?;
+
+        // The config file must be read before the flags are validated, so
+        // look for the flag ahead of parsing, splitting clusters such as
+        // "-vCapp.toml" like parsing does. The last occurrence wins.
+        let no_globals = HashMap::new();
+        let mut path = None;
+        let mut tokens = tokens.iter();
+        while let Some(token) = tokens.next() {
+            if token.value == "--" {
+                break;
+            }
+            if !utils::is_flag(&token.value) || self.is_negative_positional(token, &no_globals) {
+                continue;
+            }
+            // Unknown flags are reported when parsing
+            let Ok(found) = self.find_flags(token, &no_globals) else {
+                continue;
+            };
+            for found_flag in found {
+                if found_flag.config.arg_type.is_none() {
+                    continue;
+                }
+                let value = found_flag.value.or_else(|| {
+                    tokens
+                        .as_slice()
+                        .first()
+                        .filter(|next| self.is_flag_value(found_flag.config, next, &no_globals))
+                        .and_then(|_| tokens.next().cloned())
+                });
+                if found_flag.config.name == flag.name {
+                    path = value.map(|value| PathBuf::from(value.value));
+                }
+            }
+        }
+
+        path.or_else(|| {
+            let env = flag.env.as_ref()?;
+            env::var_os(env).map(PathBuf::from)
+        })
+    }
+
+    /// Read the config file, if there is one, and parse its values
+    pub(crate) fn load_config(&self, tokens: &[Token]) -> Result<ConfigValues, Error> {
+        let (path, explicit) = match self.config_file_path(tokens) {
+            Some(path) => (path, true),
+            None => match &self.default_config_file {
+                Some(path) => (path.clone(), false),
+                None => return Ok(ConfigValues::default()),
+            },
+        };
+        let file = path.display().to_string();
+        let error = |detail: String, line: Option<usize>| {
+            Error::new(ErrorKind::ConfigFile)
+                .with_detail(detail)
+                .with_location(&file, line)
+        };
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if !explicit && e.kind() == io::ErrorKind::NotFound => {
+                return Ok(ConfigValues::default())
+            }
+            Err(e) => return Err(error(e.to_string(), None)),
+        };
+        let document = match Format::of(&path) {
+            Some(Format::Toml) => parse_toml(&text),
+            Some(Format::Ini) => parse_ini(&text),
+            Some(Format::Json) => parse_json(&text),
+            None => {
+                return Err(error(
+                    "expected a .toml, .ini or .json file".to_string(),
+                    None,
+                ))
+            }
+        }
+        .map_err(|(line, detail)| error(detail, Some(line)))?;
+
+        self.config_values(&document, &file)
+    }
+
+    /// Match the entries of the document to flags and parse their values
+    fn config_values(&self, document: &Document, file: &str) -> Result<ConfigValues, Error> {
+        for section in &document.sections {
+            self.config_section(&section.path)
+                .map_err(|e| e.with_location(file, Some(section.line)))?;
+        }
+
+        let mut config = ConfigValues::default();
+        for entry in &document.entries {
+            let (name, value) = self
+                .config_entry(entry)
+                .map_err(|e| e.with_location(file, Some(entry.line)))?;
+
+            let mut level = &mut config;
+            for subcommand in &entry.section {
+                level = level.subcommands.entry(subcommand.clone()).or_default();
+            }
+            level.values.insert(name, value);
+        }
+        Ok(config)
+    }
+
+    /// The subcommand a section holds the flags of
+    fn config_section(&self, path: &[String]) -> Result<&Parser, Error> {
+        let mut parser = self;
+        for name in path {
+            parser = parser.find_subcommand(name).ok_or_else(|| {
+                let error = Error::new(ErrorKind::UnknownSubcommand).with_token(name);
+                let candidates = parser.subcommands.iter().map(|sub| sub.command.as_str());
+                match suggest::did_you_mean(name, candidates) {
+                    Some(suggestion) => error.with_detail(suggestion),
+                    None => error,
+                }
+            })?;
+        }
+        Ok(parser)
+    }
+
+    /// The name of the arg an entry sets and its parsed value
+    fn config_entry(&self, entry: &Entry) -> Result<(String, Arg), Error> {
+        let parser = self.config_section(&entry.section)?;
+        let arg = parser
+            .flag_configs
+            .iter()
+            .find(|flag| config_key(flag) == entry.key)
+            .ok_or_else(|| {
+                let error = Error::new(ErrorKind::UnknownFlag).with_token(&entry.key);
+                let candidates = parser.flag_configs.iter().map(|flag| config_key(flag));
+                match suggest::did_you_mean(&entry.key, candidates) {
+                    Some(suggestion) => error.with_detail(suggestion),
+                    None => error,
+                }
+            })?;
+
+        let token = |value: &str| Token {
+            value: value.to_owned(),
+            index: 0,
+            attached: true,
+        };
+        let invalid = |value: &str, detail: &str| {
+            Error::new(ErrorKind::IncorrectArgType)
+                .with_token(value)
+                .with_arg(&arg.display_name())
+                .with_detail(detail.to_string())
+        };
+        let single = || match &entry.values[..] {
+            [value] => Ok(value.as_str()),
+            values => Err(invalid(&values.join(", "), "expected a single value")),
+        };
+
+        let value = match (&arg.arg_type, arg.action) {
+            (None, Action::Count) => {
+                let value = single()?;
+                let count = value
+                    .parse::<u64>()
+                    .map_err(|_| invalid(value, "expected a count"))?;
+                Arg::Unsigned64(count)
+            }
+            (None, _) => {
+                let value = single()?;
+                let flag = utils::parse_bool(value)
+                    .ok_or_else(|| invalid(value, "expected true or false"))?;
+                Arg::Boolean(flag)
+            }
+            (Some(arg_type), Action::Append) => {
+                let mut values = vec![];
+                for value in &entry.values {
+                    values.extend(parser.parse_flag_arg_values(arg, arg_type, &token(value))?);
+                }
+                Arg::List(values)
+            }
+            (Some(arg_type), _) => parser.parse_flag_arg_value(arg, arg_type, &token(single()?))?,
+        };
+        Ok((arg.name.clone(), value))
+    }
+}
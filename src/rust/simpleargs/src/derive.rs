@@ -0,0 +1,92 @@
+use std::{process::exit, str::FromStr};
+
+use super::{help, Arg, Error, ErrorKind, HelpStyle, Matches, Parser};
+
+/// A struct that can be filled from the command line arguments.
+///
+/// Usually implemented with #[derive(SimpleArgs)] from the "derive" feature,
+/// which builds the Parser from the struct's fields and doc comments.
+pub trait SimpleArgs: Sized {
+    /// The parser for the struct's fields
+    fn parser() -> Parser;
+
+    /// Fill the struct from the parsed arguments
+    fn from_matches(matches: &Matches) -> Result<Self, Error>;
+
+    /// Parse the command line arguments into the struct
+    fn try_parse(input_args: impl Iterator<Item = String>) -> Result<Self, Error> {
+        Self::from_matches(&Self::parser().try_parse(input_args)?)
+    }
+
+    /// Parse the command line arguments into the struct, printing the error
+    /// and help screen to stderr and exiting the process with status 2 if
+    /// they are invalid.
+    fn parse(input_args: impl Iterator<Item = String>) -> Self {
+        let parser = Self::parser();
+        let matches = parser.parse(input_args);
+        match Self::from_matches(&matches) {
+            Ok(args) => args,
+            Err(e) => {
+                let bin_name = help::program_name(matches.command());
+                eprintln!("Error: {}", e);
+                eprintln!("{}", parser.render_help(bin_name, HelpStyle::Short));
+                exit(2);
+            }
+        }
+    }
+}
+
+/// Functions called by the code #[derive(SimpleArgs)] generates
+#[doc(hidden)]
+pub mod derive_support {
+    use super::*;
+
+    /// Convert a parsed Arg to the field's type through its string form
+    fn convert<T: FromStr>(arg: &Arg, display_name: &str) -> Result<T, Error> {
+        let value = arg.to_string();
+        value.parse::<T>().map_err(|_| {
+            Error::new(ErrorKind::IncorrectArgType)
+                .with_token(&value)
+                .with_arg(display_name)
+        })
+    }
+
+    /// The value of a boolean flag
+    pub fn get_flag(matches: &Matches, name: &str) -> bool {
+        matches!(matches.get_arg(name), Some(Arg::Boolean(true)))
+    }
+
+    /// The value of an optional flag or positional argument
+    pub fn get_value<T: FromStr>(
+        matches: &Matches,
+        name: &str,
+        display_name: &str,
+    ) -> Result<Option<T>, Error> {
+        match matches.get_arg(name) {
+            None | Some(Arg::None) => Ok(None),
+            Some(arg) => convert(arg, display_name).map(Some),
+        }
+    }
+
+    /// The value of a required flag or positional argument
+    pub fn get_required<T: FromStr>(
+        matches: &Matches,
+        name: &str,
+        display_name: &str,
+    ) -> Result<T, Error> {
+        get_value(matches, name, display_name)?
+            .ok_or_else(|| Error::new(ErrorKind::MissingRequired).with_token(display_name))
+    }
+
+    /// The values of an Append flag or variadic positional argument
+    pub fn get_values<T: FromStr>(
+        matches: &Matches,
+        name: &str,
+        display_name: &str,
+    ) -> Result<Vec<T>, Error> {
+        match matches.get_arg(name) {
+            Some(Arg::List(args)) => args.iter().map(|arg| convert(arg, display_name)).collect(),
+            _ => Ok(vec![]),
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use super::{completions::levels, Action, ArgConfig, Parser};
+
+/// Escape text for roff: backslashes, dashes (so they aren't hyphens) and
+/// lines starting with a control character
+fn roff_escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    escaped
+        .lines()
+        .map(|line| {
+            if line.starts_with(['.', '\'']) {
+                format!("\\&{}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// The flag names, e.g. ["-v", "--verbose"] or ["--[no-]color"]
+pub(crate) fn flag_names(flag: &ArgConfig) -> Vec<String> {
+    let mut names = vec![];
+    if let Some(short_flag) = flag.short_flag {
+        names.push(format!("-{}", short_flag));
+    }
+    if let Some(long_flag) = &flag.long_flag {
+        if flag.negatable {
+            names.push(format!("--[no-]{}", long_flag));
+        } else {
+            names.push(format!("--{}", long_flag));
+        }
+    }
+    names
+}
+
+/// The synopsis of a positional argument, e.g. "<file>", "[<file>]" or "<file>..."
+pub(crate) fn positional_synopsis(arg: &ArgConfig) -> String {
+    let ellipsis = if arg.action == Action::Append {
+        "..."
+    } else {
+        ""
+    };
+    if arg.required || arg.min_occurrences > 0 {
+        format!("{}{}", arg.value_placeholder(), ellipsis)
+    } else {
+        format!("[{}{}]", arg.value_placeholder(), ellipsis)
+    }
+}
+
+/// How an argument is written in a synopsis, e.g. "--count <count>",
+/// "--quiet" or "<file>"
+pub(crate) fn arg_synopsis(arg: &ArgConfig) -> String {
+    if arg.is_positional() {
+        return arg.value_placeholder();
+    }
+    let name = flag_names(arg).pop().unwrap_or_default();
+    match arg.arg_type {
+        Some(_) => format!("{} {}", name, arg.value_placeholder()),
+        None => name,
+    }
+}
+
+/// The "possible values: a, b, default: x, env: NAME" notes of an
+/// argument, if any
+pub(crate) fn annotations(arg: &ArgConfig) -> Vec<String> {
+    let mut notes = vec![];
+    if arg.required {
+        notes.push("required".to_string());
+    }
+    if let Some(arg_type) = &arg.arg_type {
+        let possible_values = arg_type.value_parser().possible_values();
+        if !possible_values.is_empty() {
+            notes.push(format!("possible values: {}", possible_values.join(", ")));
+        }
+    }
+    if let Some(default_value) = &arg.default_value {
+        notes.push(format!("default: {}", default_value));
+    }
+    if let Some(env) = &arg.env {
+        notes.push(format!("env: {}", env));
+    }
+    notes
+}
+
+impl Parser {
+    /// Add an example invocation shown in the help screen, man page and
+    /// Markdown reference, e.g. ("ping -c 3 127.0.0.1", "Ping localhost 3 times")
+    pub fn example(mut self, command: String, description: String) -> Self {
+        self.examples.push((command, description));
+        self
+    }
+
+    /// The arguments of the synopsis line, e.g. ["[options]", "<subcommand>"]
+    pub(crate) fn synopsis(&self) -> Vec<String> {
+        let mut synopsis = vec!["[options]".to_string()];
+        for flag in &self.flag_configs {
+            if flag.required {
+                synopsis.push(arg_synopsis(flag));
+            }
+        }
+        synopsis.extend(self.group_synopsis());
+        if !self.subcommands.is_empty() {
+            synopsis.push("<subcommand>".to_string());
+        }
+        synopsis.extend(self.pos_arg_configs.iter().map(positional_synopsis));
+        synopsis
+    }
+
+    /// Render the parser and its subcommands as a roff man page for
+    /// bin_name in the given manual section, e.g. "man ./ping.1"
+    ///
+    /// ```
+    /// let parser = simpleargs::new("Send ICMP echo requests".to_string());
+    /// let man_page = parser.man_page("ping", 8);
+    /// assert!(man_page.starts_with(".TH PING 8\n"));
+    /// ```
+    pub fn man_page(&self, bin_name: &str, section: u8) -> String {
+        let mut page = format!(".TH {} {}\n", bin_name.to_uppercase(), section);
+        page.push_str(".SH NAME\n");
+        page.push_str(&format!(
+            "{} \\- {}\n",
+            roff_escape(bin_name),
+            roff_escape(&self.description)
+        ));
+
+        for level in levels(self) {
+            let mut command_path = vec![bin_name];
+            command_path.extend(&level.path);
+            let command_path = command_path.join(" ");
+            let parser = level.parser;
+
+            if level.path.is_empty() {
+                page.push_str(".SH SYNOPSIS\n");
+            } else {
+                page.push_str(&format!(".SH \"{}\"\n", roff_escape(&command_path)));
+                page.push_str(&format!("{}\n.PP\n", roff_escape(&parser.description)));
+            }
+            page.push_str(&format!(
+                "\\fB{}\\fR {}\n",
+                roff_escape(&command_path),
+                roff_escape(&parser.synopsis().join(" "))
+            ));
+
+            if !parser.flag_configs.is_empty() {
+                page.push_str(if level.path.is_empty() {
+                    ".SH OPTIONS\n"
+                } else {
+                    ".SS OPTIONS\n"
+                });
+                for flag in &parser.flag_configs {
+                    let names: Vec<String> = flag_names(flag)
+                        .iter()
+                        .map(|name| format!("\\fB{}\\fR", roff_escape(name)))
+                        .collect();
+                    page.push_str(&format!(".TP\n{}", names.join(", ")));
+                    if flag.arg_type.is_some() {
+                        let value_name = flag.value_name.as_ref().unwrap_or(&flag.name);
+                        page.push_str(&format!(" \\fI{}\\fR", roff_escape(value_name)));
+                    }
+                    page.push_str(&format!("\n{}\n", roff_escape(&flag.description)));
+                    page.push_str(&roff_annotations(flag));
+                }
+            }
+
+            if !parser.pos_arg_configs.is_empty() {
+                page.push_str(if level.path.is_empty() {
+                    ".SH ARGUMENTS\n"
+                } else {
+                    ".SS ARGUMENTS\n"
+                });
+                for arg in &parser.pos_arg_configs {
+                    page.push_str(&format!(
+                        ".TP\n\\fI{}\\fR\n{}\n",
+                        roff_escape(&positional_synopsis(arg)),
+                        roff_escape(&arg.description)
+                    ));
+                    page.push_str(&roff_annotations(arg));
+                }
+            }
+
+            if !parser.subcommands.is_empty() {
+                page.push_str(if level.path.is_empty() {
+                    ".SH SUBCOMMANDS\n"
+                } else {
+                    ".SS SUBCOMMANDS\n"
+                });
+                for subcommand in &parser.subcommands {
+                    page.push_str(&format!(
+                        ".TP\n\\fB{}\\fR\n{}\n",
+                        roff_escape(&subcommand.command),
+                        roff_escape(&subcommand.description)
+                    ));
+                }
+            }
+
+            if !parser.examples.is_empty() {
+                page.push_str(if level.path.is_empty() {
+                    ".SH EXAMPLES\n"
+                } else {
+                    ".SS EXAMPLES\n"
+                });
+                for (command, description) in &parser.examples {
+                    page.push_str(&format!(
+                        ".TP\n\\fB{}\\fR\n{}\n",
+                        roff_escape(command),
+                        roff_escape(description)
+                    ));
+                }
+            }
+        }
+
+        page
+    }
+
+    /// Render the parser and its subcommands as a Markdown reference for
+    /// bin_name, e.g. for a README section
+    ///
+    /// ```
+    /// let parser = simpleargs::new("Send ICMP echo requests".to_string());
+    /// let markdown = parser.markdown("ping");
+    /// assert!(markdown.starts_with("# ping\n"));
+    /// ```
+    pub fn markdown(&self, bin_name: &str) -> String {
+        let mut markdown = String::new();
+
+        for level in levels(self) {
+            let mut command_path = vec![bin_name];
+            command_path.extend(&level.path);
+            let command_path = command_path.join(" ");
+            let parser = level.parser;
+
+            // Subcommands are nested one heading level deeper than their parent
+            let depth = (level.path.len() + 1).min(5);
+            let heading = "#".repeat(depth);
+            let subheading = "#".repeat(depth + 1);
+
+            if !markdown.is_empty() {
+                markdown.push('\n');
+            }
+            markdown.push_str(&format!("{} {}\n\n", heading, command_path));
+            markdown.push_str(&format!("{}\n\n", parser.description));
+            markdown.push_str(&format!(
+                "```\n{} {}\n```\n",
+                command_path,
+                parser.synopsis().join(" ")
+            ));
+
+            if !parser.flag_configs.is_empty() {
+                markdown.push_str(&format!("\n{} Options\n\n", subheading));
+                for flag in &parser.flag_configs {
+                    let mut names = flag_names(flag).join(", ");
+                    if flag.arg_type.is_some() {
+                        names.push(' ');
+                        names.push_str(&flag.value_placeholder());
+                    }
+                    markdown.push_str(&format!(
+                        "- `{}`: {}{}\n",
+                        names,
+                        flag.description,
+                        markdown_annotations(flag)
+                    ));
+                }
+            }
+
+            if !parser.pos_arg_configs.is_empty() {
+                markdown.push_str(&format!("\n{} Arguments\n\n", subheading));
+                for arg in &parser.pos_arg_configs {
+                    markdown.push_str(&format!(
+                        "- `{}`: {}{}\n",
+                        positional_synopsis(arg),
+                        arg.description,
+                        markdown_annotations(arg)
+                    ));
+                }
+            }
+
+            if !parser.subcommands.is_empty() {
+                markdown.push_str(&format!("\n{} Subcommands\n\n", subheading));
+                for subcommand in &parser.subcommands {
+                    markdown.push_str(&format!(
+                        "- `{}`: {}\n",
+                        subcommand.command, subcommand.description
+                    ));
+                }
+            }
+
+            if !parser.examples.is_empty() {
+                markdown.push_str(&format!("\n{} Examples\n", subheading));
+                for (command, description) in &parser.examples {
+                    markdown.push_str(&format!("\n{}\n\n```sh\n{}\n```\n", description, command));
+                }
+            }
+        }
+
+        markdown
+    }
+}
+
+/// The annotations of an argument as an indented roff paragraph
+fn roff_annotations(arg: &ArgConfig) -> String {
+    let notes = annotations(arg);
+    if notes.is_empty() {
+        "".to_string()
+    } else {
+        format!(".IP\n[{}]\n", roff_escape(&notes.join(", ")))
+    }
+}
+
+/// The annotations of an argument appended to its Markdown list item
+fn markdown_annotations(arg: &ArgConfig) -> String {
+    let notes = annotations(arg);
+    if notes.is_empty() {
+        "".to_string()
+    } else {
+        format!(" ({})", notes.join(", "))
+    }
+}
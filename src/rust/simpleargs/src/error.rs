@@ -0,0 +1,233 @@
+use std::fmt;
+
+/// The category of a parsing failure
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ErrorKind {
+    /// The parser configuration itself is invalid. This is a bug in the
+    /// program using simpleargs, not in the user's input.
+    InvalidConfig,
+    /// No arguments were given, not even the command
+    MissingCommand,
+    /// A flag was passed that was never defined
+    UnknownFlag,
+    /// A positional argument was passed where a subcommand was expected
+    UnknownSubcommand,
+    /// A required flag or positional argument was not passed
+    MissingRequired,
+    /// None of the arguments of a required group, or of a group another
+    /// argument requires, was passed
+    MissingGroup,
+    /// Two arguments that can't be used together were both passed
+    ArgumentConflict,
+    /// A flag that takes a value was passed without one
+    MissingValue,
+    /// The value could not be parsed as the configured ArgType
+    IncorrectArgType,
+    /// The value is outside of the range configured for the argument
+    OutOfRange,
+    /// The value of a flag looks like a flag itself
+    ArgValueIsFlag,
+    /// More positional arguments were passed than configured
+    TooManyPositionals,
+    /// A flag was passed fewer times than its min_occurrences
+    TooFewOccurrences,
+    /// A flag was passed more times than its max_occurrences
+    TooManyOccurrences,
+    /// A parsed value was requested as a type it can't be converted to
+    TypeMismatch,
+    /// The config file couldn't be read or has a syntax error
+    ConfigFile,
+    /// A response file couldn't be read, has an unterminated quote or
+    /// includes itself
+    ResponseFile,
+    /// "--help" was passed. Not a failure, but parsing stops here.
+    HelpRequested,
+    /// "--generate-completions <shell>" was passed. Not a failure either,
+    /// Error::help holds the completion script.
+    CompletionsRequested,
+}
+
+/// Where the offending token is
+#[derive(PartialEq, Debug)]
+enum Position {
+    /// Index of the token in argv (the command is index 0)
+    Argument(usize),
+    /// The config file and line of the value. Boxed since few errors have one.
+    File(Box<(String, Option<usize>)>),
+}
+
+/// An error returned by Parser::try_parse
+#[derive(PartialEq, Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    /// The offending token, e.g. "--flag" or "abc"
+    token: Option<String>,
+    /// Where the offending token is in argv or the config file
+    position: Option<Position>,
+    /// The flag or positional argument the token was meant for
+    arg: Option<String>,
+    /// Extra explanation appended to the message
+    detail: Option<String>,
+    /// Help screen of the (sub)command being parsed when the error occurred
+    help: Option<String>,
+}
+
+impl Error {
+    pub(crate) fn new(kind: ErrorKind) -> Self {
+        Error {
+            kind,
+            token: None,
+            position: None,
+            arg: None,
+            detail: None,
+            help: None,
+        }
+    }
+
+    pub(crate) fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_owned());
+        self
+    }
+
+    pub(crate) fn with_index(mut self, index: usize) -> Self {
+        self.position = Some(Position::Argument(index));
+        self
+    }
+
+    pub(crate) fn with_arg(mut self, arg: &str) -> Self {
+        self.arg = Some(arg.to_owned());
+        self
+    }
+
+    pub(crate) fn with_detail(mut self, detail: String) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    /// Point the error at a line of a config file instead of an argv index
+    pub(crate) fn with_location(mut self, file: &str, line: Option<usize>) -> Self {
+        self.position = Some(Position::File(Box::new((file.to_owned(), line))));
+        self
+    }
+
+    /// Set the help screen unless a more specific one was already set
+    pub(crate) fn with_help(mut self, help: String) -> Self {
+        if self.help.is_none() {
+            self.help = Some(help);
+        }
+        self
+    }
+
+    /// What kind of error occurred
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The command line token that caused the error, if any
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// The argv index of the token that caused the error, if any
+    pub fn index(&self) -> Option<usize> {
+        match self.position {
+            Some(Position::Argument(index)) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// The flag or positional argument the offending token was meant for
+    pub fn arg(&self) -> Option<&str> {
+        self.arg.as_deref()
+    }
+
+    /// Extra explanation of the error, if any
+    pub fn detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+
+    /// The config file that caused the error, if any
+    pub fn file(&self) -> Option<&str> {
+        match &self.position {
+            Some(Position::File(location)) => Some(location.0.as_str()),
+            _ => None,
+        }
+    }
+
+    /// The line of the config file that caused the error, if known
+    pub fn line(&self) -> Option<usize> {
+        match &self.position {
+            Some(Position::File(location)) => location.1,
+            _ => None,
+        }
+    }
+
+    /// The help screen of the (sub)command that failed to parse, or the
+    /// completion script for ErrorKind::CompletionsRequested
+    pub fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let token = self.token.as_deref().unwrap_or("");
+        match self.kind {
+            ErrorKind::InvalidConfig => write!(f, "invalid configuration for '{}'", token)?,
+            ErrorKind::MissingCommand => {
+                write!(f, "must have at least one argument (the command)")?
+            }
+            ErrorKind::UnknownFlag => write!(f, "flag '{}' is not defined", token)?,
+            ErrorKind::UnknownSubcommand => write!(f, "unknown subcommand '{}'", token)?,
+            ErrorKind::MissingRequired => write!(f, "missing required argument '{}'", token)?,
+            ErrorKind::MissingGroup => write!(f, "one of '{}' is required", token)?,
+            ErrorKind::ArgumentConflict => match &self.arg {
+                Some(arg) => write!(f, "'{}' cannot be used with '{}'", token, arg)?,
+                None => write!(f, "'{}' cannot be used here", token)?,
+            },
+            ErrorKind::MissingValue => write!(f, "flag '{}' expects a value", token)?,
+            ErrorKind::IncorrectArgType => match &self.arg {
+                Some(arg) => write!(f, "invalid value '{}' for '{}'", token, arg)?,
+                None => write!(f, "invalid value '{}'", token)?,
+            },
+            ErrorKind::OutOfRange => match &self.arg {
+                Some(arg) => write!(f, "value '{}' for '{}' is out of range", token, arg)?,
+                None => write!(f, "value '{}' is out of range", token)?,
+            },
+            ErrorKind::ArgValueIsFlag => match &self.arg {
+                Some(arg) => write!(f, "'{}' expects a value but got the flag '{}'", arg, token)?,
+                None => write!(f, "expected a value but got the flag '{}'", token)?,
+            },
+            ErrorKind::TooManyPositionals => {
+                write!(f, "unexpected positional argument '{}'", token)?
+            }
+            ErrorKind::TooFewOccurrences => write!(f, "'{}' was passed too few times", token)?,
+            ErrorKind::TooManyOccurrences => write!(f, "'{}' was passed too many times", token)?,
+            ErrorKind::TypeMismatch => match &self.arg {
+                Some(arg) => write!(f, "can't read '{}' as the requested type", arg)?,
+                None => write!(f, "can't read the value as the requested type")?,
+            },
+            ErrorKind::ConfigFile => write!(f, "can't load the config file")?,
+            ErrorKind::ResponseFile => write!(f, "can't expand response file '{}'", token)?,
+            ErrorKind::HelpRequested => write!(f, "help requested")?,
+            ErrorKind::CompletionsRequested => write!(f, "completions requested")?,
+        }
+
+        if let Some(detail) = &self.detail {
+            write!(f, ": {}", detail)?;
+        }
+
+        match &self.position {
+            Some(Position::Argument(index)) => write!(f, " (argument {})", index)?,
+            Some(Position::File(location)) => match location.as_ref() {
+                (file, Some(line)) => write!(f, " ({}:{})", file, line)?,
+                (file, None) => write!(f, " ({})", file)?,
+            },
+            None => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,166 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::PathBuf,
+};
+
+use super::Arg;
+
+/// Conversion from a parsed Arg to a Rust type, used by the typed getters
+/// Matches::get, Matches::get_or and Matches::get_many.
+///
+/// Implement it to read your own types:
+///
+/// ```
+/// use simpleargs::{Arg, FromArg};
+///
+/// struct Port(u16);
+///
+/// impl FromArg for Port {
+///     fn from_arg(arg: &Arg) -> Option<Self> {
+///         u16::from_arg(arg).map(Port)
+///     }
+/// }
+/// ```
+pub trait FromArg: Sized {
+    /// Convert the arg, or None if it holds a different type of value
+    fn from_arg(arg: &Arg) -> Option<Self>;
+}
+
+impl FromArg for char {
+    fn from_arg(arg: &Arg) -> Option<Self> {
+        match arg {
+            Arg::Character(val) => Some(*val),
+            _ => None,
+        }
+    }
+}
+
+impl FromArg for f32 {
+    fn from_arg(arg: &Arg) -> Option<Self> {
+        match arg {
+            Arg::Float(val) => Some(*val),
+            _ => None,
+        }
+    }
+}
+
+impl FromArg for f64 {
+    fn from_arg(arg: &Arg) -> Option<Self> {
+        match arg {
+            Arg::Float(val) => Some(f64::from(*val)),
+            Arg::Double(val) => Some(*val),
+            _ => None,
+        }
+    }
+}
+
+/// The value of any of the integer Arg variants
+fn integer_value(arg: &Arg) -> Option<i128> {
+    match arg {
+        Arg::Integer(val) => Some(i128::from(*val)),
+        Arg::Integer64(val) => Some(i128::from(*val)),
+        Arg::Unsigned8(val) => Some(i128::from(*val)),
+        Arg::Unsigned16(val) => Some(i128::from(*val)),
+        Arg::Unsigned32(val) => Some(i128::from(*val)),
+        Arg::Unsigned64(val) => Some(i128::from(*val)),
+        _ => None,
+    }
+}
+
+/// Integers convert from any of the integer Arg variants if the value fits
+macro_rules! impl_from_arg_for_integer {
+    ($($int:ty),*) => {
+        $(
+            impl FromArg for $int {
+                fn from_arg(arg: &Arg) -> Option<Self> {
+                    <$int>::try_from(integer_value(arg)?).ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_from_arg_for_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl FromArg for String {
+    fn from_arg(arg: &Arg) -> Option<Self> {
+        match arg {
+            Arg::String(val) => Some(val.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromArg for bool {
+    fn from_arg(arg: &Arg) -> Option<Self> {
+        match arg {
+            Arg::Boolean(val) => Some(*val),
+            _ => None,
+        }
+    }
+}
+
+/// An enum whose variants are the choices of an argument. Parse them with
+/// ChoiceParser::of and read them with the typed getters.
+///
+/// ```
+/// use simpleargs::{ArgConfig, ArgType, ChoiceParser, ValueEnum};
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// enum Format {
+///     Text,
+///     Json,
+/// }
+///
+/// impl ValueEnum for Format {
+///     fn variants() -> Vec<(&'static str, Self)> {
+///         vec![("text", Format::Text), ("json", Format::Json)]
+///     }
+/// }
+///
+/// let args = ["tool", "--format", "json"].map(String::from);
+/// let matches = simpleargs::new("example".to_string())
+///     .add_arg(
+///         ArgConfig::new("format".to_string())
+///             .long_flag("format".to_string())
+///             .arg_type(ArgType::custom(ChoiceParser::of::<Format>())),
+///     )
+///     .try_parse(args.into_iter())
+///     .unwrap();
+///
+/// assert_eq!(matches.get::<Format>("format").unwrap(), Some(Format::Json));
+/// ```
+pub trait ValueEnum: Sized + Clone {
+    /// Every variant and the value it is passed as, e.g. ("json", Format::Json)
+    fn variants() -> Vec<(&'static str, Self)>;
+}
+
+impl<T: ValueEnum> FromArg for T {
+    fn from_arg(arg: &Arg) -> Option<Self> {
+        match arg {
+            Arg::String(val) => T::variants()
+                .into_iter()
+                .find(|(name, _)| name == val)
+                .map(|(_, variant)| variant),
+            _ => None,
+        }
+    }
+}
+
+/// Types produced by FromStrParser convert from the Arg::Custom holding them
+macro_rules! impl_from_arg_for_custom {
+    ($($custom:ty),*) => {
+        $(
+            impl FromArg for $custom {
+                fn from_arg(arg: &Arg) -> Option<Self> {
+                    match arg {
+                        Arg::Custom(val) => val.downcast_ref::<$custom>().cloned(),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_arg_for_custom!(IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, PathBuf);
@@ -0,0 +1,233 @@
+use std::{collections::HashMap, rc::Rc};
+
+use super::{docs, ArgConfig, Error, ErrorKind, Matches, Parser, ValueSource};
+
+/// A named set of arguments checked together after parsing. By default at
+/// most one of them may be passed. Values from the config file satisfy
+/// required groups but never conflict with the command line.
+///
+/// "Exactly one of --file or --url":
+///
+/// ```
+/// use simpleargs::{ArgConfig, ArgGroup, ArgType};
+///
+/// let parser = simpleargs::new("Download a list".to_string())
+///     .add_arg(
+///         ArgConfig::new("file".to_string())
+///             .long_flag("file".to_string())
+///             .arg_type(ArgType::String),
+///     )
+///     .add_arg(
+///         ArgConfig::new("url".to_string())
+///             .long_flag("url".to_string())
+///             .arg_type(ArgType::String),
+///     )
+///     .add_group(
+///         ArgGroup::new("source".to_string())
+///             .arg("file".to_string())
+///             .arg("url".to_string())
+///             .required(true),
+///     );
+///
+/// let args = ["fetch", "--file", "a", "--url", "b"].map(String::from);
+/// assert!(parser.try_parse(args.into_iter()).is_err());
+/// ```
+pub struct ArgGroup {
+    pub(crate) name: String,
+    /// Names of the member arguments
+    pub(crate) args: Vec<String>,
+    /// At least one member must be passed
+    pub(crate) required: bool,
+    /// More than one member may be passed
+    pub(crate) multiple: bool,
+}
+
+impl ArgGroup {
+    /// Start a group. Its name can be used in ArgConfig::requires and
+    /// ArgConfig::conflicts_with like an argument name.
+    pub fn new(name: String) -> Self {
+        ArgGroup {
+            name,
+            args: vec![],
+            required: false,
+            multiple: false,
+        }
+    }
+
+    /// Add the argument called name to the group
+    pub fn arg(mut self, name: String) -> Self {
+        self.args.push(name);
+        self
+    }
+
+    /// Fail parsing unless at least one member is passed
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Allow passing more than one member
+    pub fn multiple(mut self, multiple: bool) -> Self {
+        self.multiple = multiple;
+        self
+    }
+}
+
+/// Whether the argument was given by the user, on the command line or in
+/// its environment variable. Only these values can conflict: values from
+/// the config file just fill in what isn't given.
+fn is_given(name: &str, matches: &Matches, global_matches: &Matches) -> bool {
+    let source = matches.source(name).or_else(|| global_matches.source(name));
+    matches!(
+        source,
+        Some(ValueSource::CommandLine | ValueSource::Environment)
+    )
+}
+
+/// Whether the argument has a value the user chose, given or from the
+/// config file. Default values don't count.
+fn is_set(name: &str, matches: &Matches, global_matches: &Matches) -> bool {
+    is_given(name, matches, global_matches)
+        || matches.source(name).or_else(|| global_matches.source(name))
+            == Some(ValueSource::ConfigFile)
+}
+
+impl Parser {
+    /// Check arguments against their groups in the parser
+    pub fn add_group(mut self, group: ArgGroup) -> Self {
+        self.groups.push(group);
+        self
+    }
+
+    /// The argument called name, looking in the global flags of the parent
+    /// parsers as well
+    fn find_arg<'a>(
+        &'a self,
+        name: &str,
+        globals: &'a HashMap<String, Rc<ArgConfig>>,
+    ) -> Option<&'a ArgConfig> {
+        self.flag_configs
+            .iter()
+            .map(|flag| flag.as_ref())
+            .chain(self.pos_arg_configs.iter())
+            .chain(globals.values().map(|flag| flag.as_ref()))
+            .find(|arg| arg.name == name)
+    }
+
+    /// The arguments a name in requires or conflicts_with stands for: the
+    /// members of the group called name, or the argument called name
+    fn resolve_args<'a>(
+        &'a self,
+        name: &str,
+        globals: &'a HashMap<String, Rc<ArgConfig>>,
+    ) -> Result<Vec<&'a ArgConfig>, Error> {
+        let invalid = |name: &str| Error::new(ErrorKind::InvalidConfig).with_token(name);
+        match self.groups.iter().find(|group| group.name == name) {
+            Some(group) => group
+                .args
+                .iter()
+                .map(|arg| self.find_arg(arg, globals).ok_or_else(|| invalid(arg)))
+                .collect(),
+            None => Ok(vec![self
+                .find_arg(name, globals)
+                .ok_or_else(|| invalid(name))?]),
+        }
+    }
+
+    /// Check the groups and the requires and conflicts_with relationships
+    /// of the parsed arguments
+    pub(crate) fn validate_relationships(
+        &self,
+        globals: &HashMap<String, Rc<ArgConfig>>,
+        matches: &Matches,
+        global_matches: &Matches,
+    ) -> Result<(), Error> {
+        let given = |arg: &&ArgConfig| is_given(&arg.name, matches, global_matches);
+        let set = |arg: &&ArgConfig| is_set(&arg.name, matches, global_matches);
+
+        for group in &self.groups {
+            let members = self.resolve_args(&group.name, globals)?;
+            let passed: Vec<&ArgConfig> = members.iter().copied().filter(given).collect();
+            if let [first, second, ..] = passed[..] {
+                if !group.multiple {
+                    return Err(Error::new(ErrorKind::ArgumentConflict)
+                        .with_token(&second.display_name())
+                        .with_arg(&first.display_name()));
+                }
+            }
+            if group.required && !members.iter().any(set) {
+                return Err(Error::new(ErrorKind::MissingGroup)
+                    .with_token(&group_display_name(&members))
+                    .with_arg(&group.name));
+            }
+        }
+
+        let args = self
+            .flag_configs
+            .iter()
+            .map(|flag| flag.as_ref())
+            .chain(self.pos_arg_configs.iter());
+        for arg in args.filter(set) {
+            for name in &arg.requires {
+                let required = self.resolve_args(name, globals)?;
+                if !required.iter().any(set) {
+                    let kind = match required[..] {
+                        [_] => ErrorKind::MissingRequired,
+                        _ => ErrorKind::MissingGroup,
+                    };
+                    return Err(Error::new(kind)
+                        .with_token(&group_display_name(&required))
+                        .with_detail(format!("required by '{}'", arg.display_name())));
+                }
+            }
+            if !given(&arg) {
+                continue;
+            }
+            for name in &arg.conflicts_with {
+                let conflicting = self.resolve_args(name, globals)?;
+                if let Some(other) = conflicting.iter().find(|other| given(other)) {
+                    return Err(Error::new(ErrorKind::ArgumentConflict)
+                        .with_token(&arg.display_name())
+                        .with_arg(&other.display_name()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The groups of the synopsis line: "(--file <file> | --url <url>)"
+    /// for a required group and "[--json | --quiet]" for a group of
+    /// mutually exclusive options
+    pub(crate) fn group_synopsis(&self) -> Vec<String> {
+        let no_globals = HashMap::new();
+        let mut synopsis = vec![];
+        for group in &self.groups {
+            if !group.required && group.multiple {
+                continue;
+            }
+            let members: Vec<String> = group
+                .args
+                .iter()
+                .filter_map(|name| self.find_arg(name, &no_globals))
+                .map(docs::arg_synopsis)
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            if group.required {
+                synopsis.push(format!("({})", members.join(" | ")));
+            } else {
+                synopsis.push(format!("[{}]", members.join(" | ")));
+            }
+        }
+        synopsis
+    }
+}
+
+/// How a set of arguments is named in errors, e.g. "--file | --url"
+fn group_display_name(args: &[&ArgConfig]) -> String {
+    args.iter()
+        .map(|arg| arg.display_name())
+        .collect::<Vec<String>>()
+        .join(" | ")
+}
@@ -0,0 +1,333 @@
+use std::{env, path::Path};
+
+use super::{docs, ArgConfig, Parser};
+
+/// How much the help screen shows
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum HelpStyle {
+    /// A line per argument, shown for "-h" and after errors
+    Short,
+    /// Value types, a paragraph per argument and the examples, shown for "--help"
+    Long,
+}
+
+/// Width used when the terminal's width can't be detected
+const DEFAULT_WIDTH: usize = 80;
+/// Narrowest width wrapped to, however narrow the terminal
+const MIN_WIDTH: usize = 40;
+/// Arguments longer than this put their description on the next line
+const MAX_NAME_WIDTH: usize = 30;
+/// Indentation of the arguments under a heading
+const INDENT: usize = 2;
+/// Indentation of the descriptions in the long help screen
+const LONG_INDENT: usize = 10;
+
+/// The width of the terminal from $COLUMNS, or from the terminal itself
+/// if standard output is one
+pub(crate) fn terminal_width() -> usize {
+    let columns = env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse::<usize>().ok());
+    columns
+        .or_else(window_width)
+        .unwrap_or(DEFAULT_WIDTH)
+        .max(MIN_WIDTH)
+}
+
+#[cfg(unix)]
+fn window_width() -> Option<usize> {
+    // Safe because isatty only reads the descriptor
+    if unsafe { libc::isatty(libc::STDOUT_FILENO) } != 1 {
+        return None;
+    }
+    // Safe because all zeroes is a valid winsize, which TIOCGWINSZ fills in
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_col > 0).then_some(usize::from(size.ws_col))
+}
+
+#[cfg(not(unix))]
+fn window_width() -> Option<usize> {
+    None
+}
+
+/// The program name to show in usage lines: argv[0] without its directory
+pub(crate) fn program_name(command: &str) -> &str {
+    Path::new(command)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(command)
+}
+
+/// Split text into lines of at most width characters, breaking between
+/// words. Words longer than width get a line of their own.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// A flag, positional argument, subcommand or example and its description
+struct Entry {
+    name: String,
+    description: String,
+    /// Notes such as "[default: 1]" shown after the description
+    notes: Vec<String>,
+}
+
+impl Entry {
+    fn for_arg(arg: &ArgConfig, style: HelpStyle) -> Self {
+        let name = if arg.is_positional() {
+            docs::positional_synopsis(arg)
+        } else {
+            let mut name = docs::flag_names(arg).join(", ");
+            // Align long flags with the long flags that follow a short one
+            if arg.short_flag.is_none() {
+                name.insert_str(0, "    ");
+            }
+            if arg.arg_type.is_some() {
+                name.push(' ');
+                name.push_str(&arg.value_placeholder());
+            }
+            name
+        };
+
+        let mut notes = vec![];
+        if let (HelpStyle::Long, Some(arg_type)) = (style, &arg.arg_type) {
+            notes.push(format!("[type: {}]", arg_type.value_parser().type_name()));
+        }
+        notes.extend(
+            docs::annotations(arg)
+                .into_iter()
+                .map(|note| format!("[{}]", note)),
+        );
+
+        Entry {
+            name,
+            description: arg.description.clone(),
+            notes,
+        }
+    }
+
+    /// The description followed by the notes
+    fn text(&self) -> String {
+        let mut text = self.description.clone();
+        for note in &self.notes {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(note);
+        }
+        text
+    }
+}
+
+/// A heading and the entries under it
+struct Section {
+    heading: String,
+    entries: Vec<Entry>,
+}
+
+/// Render the entries as "name  description" in two aligned columns
+fn render_short_entries(entries: &[Entry], width: usize) -> String {
+    let name_width = entries
+        .iter()
+        .map(|entry| entry.name.chars().count())
+        .filter(|width| *width <= MAX_NAME_WIDTH)
+        .max()
+        .unwrap_or(0);
+    let description_indent = INDENT + name_width + 2;
+    let description_width = width.saturating_sub(description_indent).max(MIN_WIDTH / 2);
+
+    let mut output = String::new();
+    for entry in entries {
+        let name_chars = entry.name.chars().count();
+        let lines = wrap(&entry.text(), description_width);
+        output.push_str(&format!("{}{}", " ".repeat(INDENT), entry.name));
+
+        let mut lines = lines.into_iter().filter(|line| !line.is_empty());
+        if name_chars <= name_width {
+            if let Some(first) = lines.next() {
+                output.push_str(&" ".repeat(description_indent - INDENT - name_chars));
+                output.push_str(&first);
+            }
+        }
+        output.push('\n');
+        for line in lines {
+            output.push_str(&format!("{}{}\n", " ".repeat(description_indent), line));
+        }
+    }
+    output
+}
+
+/// Render each entry as its name with an indented paragraph below it
+fn render_long_entries(entries: &[Entry], width: usize) -> String {
+    let description_width = width.saturating_sub(LONG_INDENT).max(MIN_WIDTH / 2);
+    let mut paragraphs = vec![];
+    for entry in entries {
+        let mut paragraph = format!("{}{}\n", " ".repeat(INDENT), entry.name);
+        for line in wrap(&entry.text(), description_width) {
+            if !line.is_empty() {
+                paragraph.push_str(&format!("{}{}\n", " ".repeat(LONG_INDENT), line));
+            }
+        }
+        paragraphs.push(paragraph);
+    }
+    paragraphs.join("\n")
+}
+
+impl Parser {
+    /// Wrap the help screen to width columns instead of the terminal's width
+    pub fn help_width(mut self, width: usize) -> Self {
+        self.help_width = Some(width);
+        self
+    }
+
+    /// Render the help screen of the parser for bin_name, the name the
+    /// program is invoked as.
+    ///
+    /// ```
+    /// use simpleargs::{ArgConfig, ArgType, HelpStyle};
+    ///
+    /// let parser = simpleargs::new("Send ICMP echo requests".to_string()).add_arg(
+    ///     ArgConfig::new("count".to_string())
+    ///         .short_flag('c')
+    ///         .arg_type(ArgType::Integer)
+    ///         .description("Stop after count replies".to_string()),
+    /// );
+    /// let help = parser.render_help("ping", HelpStyle::Short);
+    /// assert!(help.contains("Usage: ping [options]\n"));
+    /// assert!(help.contains("  -c <count>  Stop after count replies\n"));
+    /// ```
+    pub fn render_help(&self, bin_name: &str, style: HelpStyle) -> String {
+        self.render_help_for(bin_name, style, false)
+    }
+
+    /// Render the help screen. command_path is how the (sub)command is
+    /// invoked, e.g. "tool remote add". h_taken is whether a global flag
+    /// of a parent parser already uses "-h".
+    pub(crate) fn render_help_for(
+        &self,
+        command_path: &str,
+        style: HelpStyle,
+        h_taken: bool,
+    ) -> String {
+        let width = self.help_width.unwrap_or_else(terminal_width);
+        let mut help = String::new();
+        for line in wrap(&self.description, width) {
+            help.push_str(&line);
+            help.push('\n');
+        }
+        help.push_str(&format!(
+            "\nUsage: {} {}\n",
+            command_path,
+            self.synopsis().join(" ")
+        ));
+
+        let mut sections = vec![
+            Section {
+                heading: "Arguments".to_string(),
+                entries: vec![],
+            },
+            Section {
+                heading: "Options".to_string(),
+                entries: vec![],
+            },
+        ];
+        let args = self
+            .pos_arg_configs
+            .iter()
+            .chain(self.flag_configs.iter().map(|flag| flag.as_ref()));
+        for arg in args {
+            let heading = match &arg.heading {
+                Some(heading) => heading.as_str(),
+                None if arg.is_positional() => "Arguments",
+                None => "Options",
+            };
+            let entry = Entry::for_arg(arg, style);
+            match sections
+                .iter_mut()
+                .find(|section| section.heading == heading)
+            {
+                Some(section) => section.entries.push(entry),
+                None => sections.push(Section {
+                    heading: heading.to_string(),
+                    entries: vec![entry],
+                }),
+            }
+        }
+
+        let help_entry = Entry {
+            name: if self.has_short_help_flag(h_taken) {
+                "-h, --help".to_string()
+            } else {
+                "    --help".to_string()
+            },
+            description: match style {
+                HelpStyle::Short => "Print help (see more with '--help')".to_string(),
+                HelpStyle::Long => "Print help (see a summary with '-h')".to_string(),
+            },
+            notes: vec![],
+        };
+        sections[1].entries.push(help_entry);
+
+        sections.push(Section {
+            heading: "Subcommands".to_string(),
+            entries: self
+                .subcommands
+                .iter()
+                .map(|subcommand| Entry {
+                    name: subcommand.command.clone(),
+                    description: subcommand.description.clone(),
+                    notes: vec![],
+                })
+                .collect(),
+        });
+
+        if style == HelpStyle::Long {
+            sections.push(Section {
+                heading: "Examples".to_string(),
+                entries: self
+                    .examples
+                    .iter()
+                    .map(|(command, description)| Entry {
+                        name: command.clone(),
+                        description: description.clone(),
+                        notes: vec![],
+                    })
+                    .collect(),
+            });
+        }
+
+        for section in sections {
+            if section.entries.is_empty() {
+                continue;
+            }
+            help.push_str(&format!("\n{}:\n", section.heading));
+            help.push_str(&match style {
+                HelpStyle::Short => render_short_entries(&section.entries, width),
+                HelpStyle::Long => render_long_entries(&section.entries, width),
+            });
+        }
+
+        help
+    }
+
+    /// Whether "-h" prints the short help screen. It doesn't if the
+    /// parser or its parents define their own "-h" flag.
+    pub(crate) fn has_short_help_flag(&self, h_taken: bool) -> bool {
+        !h_taken && !self.flag_map.contains_key("-h")
+    }
+}
@@ -1,17 +1,41 @@
-use std::{collections::HashMap, process::exit, rc::Rc, vec};
-
+// The tests kept from the first version of the crate destructure a
+// single variant with match
+#![cfg_attr(test, allow(clippy::single_match))]
+
+use std::{collections::HashMap, env, fmt, path::PathBuf, process::exit, rc::Rc, vec};
+
+mod arg_config;
+mod completions;
+mod config;
+mod derive;
+mod docs;
+mod error;
+mod from_arg;
+mod group;
+mod help;
+mod matches;
+mod range;
+mod response_file;
+mod suggest;
 mod utils;
+mod value_parser;
+pub use arg_config::{Action, ArgConfig, ValueHint};
+pub use completions::Shell;
+use config::ConfigValues;
+pub use derive::{derive_support, SimpleArgs};
+pub use error::{Error, ErrorKind};
+pub use from_arg::{FromArg, ValueEnum};
+pub use group::ArgGroup;
+pub use help::HelpStyle;
+pub use matches::{Matches, ValueSource};
+#[cfg(feature = "derive")]
+pub use simpleargs_derive::SimpleArgs;
 use utils::*;
-
-#[derive(Debug)]
-enum ParserError {
-    FlagConfigError,
-    ArgConfigError,
-    MissingRequiredFlag,
-    //TooManyPositionalArguments,
-    ArgValueIsFlag,
-    IncorrectArgType,
-}
+pub use value_parser::{
+    CharacterParser, ChoiceParser, CustomValue, DoubleParser, FloatParser, FromStrParser,
+    Integer64Parser, IntegerParser, StringParser, Unsigned16Parser, Unsigned32Parser,
+    Unsigned64Parser, Unsigned8Parser, ValueParser,
+};
 
 #[derive(PartialEq, Debug)]
 enum FlagType {
@@ -21,66 +45,270 @@ enum FlagType {
 }
 
 /// Specify what type the argument value should be
-#[derive(PartialEq, Debug)]
+#[derive(Clone)]
 pub enum ArgType {
     Character,
+    /// f32
     Float,
+    /// f64
+    Double,
+    /// i32
     Integer,
+    /// i64
+    Integer64,
+    /// u8
+    Unsigned8,
+    /// u16
+    Unsigned16,
+    /// u32
+    Unsigned32,
+    /// u64
+    Unsigned64,
     String,
+    /// A type parsed by an application provided ValueParser
+    Custom(Rc<dyn ValueParser>),
+}
+
+impl ArgType {
+    /// An ArgType parsed by parser
+    pub fn custom(parser: impl ValueParser + 'static) -> Self {
+        ArgType::Custom(Rc::new(parser))
+    }
+
+    /// One of a fixed list of values, e.g. ["text", "json", "csv"]. Use
+    /// ChoiceParser directly to ignore case.
+    pub fn choices(choices: &[&str]) -> Self {
+        ArgType::custom(ChoiceParser::new(choices))
+    }
+
+    /// Whether values of this type are numbers, which may be negative
+    pub fn is_numeric(&self) -> bool {
+        !matches!(
+            self,
+            ArgType::Character | ArgType::String | ArgType::Custom(_)
+        )
+    }
+
+    /// Any value of this type, or None for a custom type, whose values may
+    /// be of any Arg variant
+    pub(crate) fn sample_value(&self) -> Option<Arg> {
+        match self {
+            ArgType::Character => Some(Arg::Character(' ')),
+            ArgType::Float => Some(Arg::Float(0.0)),
+            ArgType::Double => Some(Arg::Double(0.0)),
+            ArgType::Integer => Some(Arg::Integer(0)),
+            ArgType::Integer64 => Some(Arg::Integer64(0)),
+            ArgType::Unsigned8 => Some(Arg::Unsigned8(0)),
+            ArgType::Unsigned16 => Some(Arg::Unsigned16(0)),
+            ArgType::Unsigned32 => Some(Arg::Unsigned32(0)),
+            ArgType::Unsigned64 => Some(Arg::Unsigned64(0)),
+            ArgType::String => Some(Arg::String(String::new())),
+            ArgType::Custom(_) => None,
+        }
+    }
+
+    /// The parser for values of this type
+    pub fn value_parser(&self) -> &dyn ValueParser {
+        match self {
+            ArgType::Character => &CharacterParser,
+            ArgType::Float => &FloatParser,
+            ArgType::Double => &DoubleParser,
+            ArgType::Integer => &IntegerParser,
+            ArgType::Integer64 => &Integer64Parser,
+            ArgType::Unsigned8 => &Unsigned8Parser,
+            ArgType::Unsigned16 => &Unsigned16Parser,
+            ArgType::Unsigned32 => &Unsigned32Parser,
+            ArgType::Unsigned64 => &Unsigned64Parser,
+            ArgType::String => &StringParser,
+            ArgType::Custom(parser) => parser.as_ref(),
+        }
+    }
+}
+
+impl PartialEq for ArgType {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ArgType::Custom(parser), ArgType::Custom(other_parser)) => {
+                Rc::ptr_eq(parser, other_parser)
+            }
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl fmt::Debug for ArgType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value_parser().type_name())
+    }
 }
 
 /// A parsed argument
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub enum Arg {
     Character(char),
     Float(f32),
+    Double(f64),
     Integer(i32),
+    Integer64(i64),
+    Unsigned8(u8),
+    Unsigned16(u16),
+    Unsigned32(u32),
+    Unsigned64(u64),
     String(String),
     Boolean(bool),
+    /// The values of a flag with Action::Append
+    List(Vec<Arg>),
+    /// A value parsed by a custom ValueParser
+    Custom(CustomValue),
     None,
 }
 
-/// If no short_flag or long_flag then this is a positional argument.
-/// If no arg_type for short_flag or long_flag then this is boolean arg_type.
-// TODO: Support variable length positional args natively
-struct ArgConfig {
-    name: String,
-    short_flag: Option<char>,
-    long_flag: Option<String>,
-    required: bool,
-    arg_type: Option<ArgType>,
-    description: String,
+impl Arg {
+    /// Wrap a value produced by a custom ValueParser. display is how the
+    /// value is shown and converted to a string.
+    pub fn custom<T: 'static>(value: T, display: String) -> Self {
+        Arg::Custom(CustomValue::new(Rc::new(value), display))
+    }
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arg::Character(val) => write!(f, "{}", val),
+            Arg::Float(val) => write!(f, "{}", val),
+            Arg::Double(val) => write!(f, "{}", val),
+            Arg::Integer(val) => write!(f, "{}", val),
+            Arg::Integer64(val) => write!(f, "{}", val),
+            Arg::Unsigned8(val) => write!(f, "{}", val),
+            Arg::Unsigned16(val) => write!(f, "{}", val),
+            Arg::Unsigned32(val) => write!(f, "{}", val),
+            Arg::Unsigned64(val) => write!(f, "{}", val),
+            Arg::String(val) => write!(f, "{}", val),
+            Arg::Boolean(val) => write!(f, "{}", val),
+            Arg::List(vals) => {
+                let vals: Vec<String> = vals.iter().map(|val| val.to_string()).collect();
+                write!(f, "{}", vals.join(","))
+            }
+            Arg::Custom(val) => write!(f, "{}", val),
+            Arg::None => Ok(()),
+        }
+    }
+}
+
+/// Implement From<$type> for Arg wrapping the value in Arg::$variant
+macro_rules! impl_arg_from {
+    ($($type:ty => $variant:ident),*) => {
+        $(
+            impl From<$type> for Arg {
+                fn from(val: $type) -> Self {
+                    Arg::$variant(val)
+                }
+            }
+        )*
+    };
+}
+
+impl_arg_from!(
+    char => Character,
+    f32 => Float,
+    f64 => Double,
+    i32 => Integer,
+    i64 => Integer64,
+    u8 => Unsigned8,
+    u16 => Unsigned16,
+    u32 => Unsigned32,
+    u64 => Unsigned64
+);
+
+impl From<String> for Arg {
+    fn from(val: String) -> Self {
+        Arg::String(val)
+    }
+}
+
+impl From<&str> for Arg {
+    fn from(val: &str) -> Self {
+        Arg::String(val.to_owned())
+    }
+}
+
+impl From<bool> for Arg {
+    fn from(val: bool) -> Self {
+        Arg::Boolean(val)
+    }
+}
+
+/// A single command line argument and where it came from in argv
+#[derive(Clone)]
+struct Token {
+    value: String,
+    /// Index into argv. The command is index 0.
+    index: usize,
+    /// Split from its flag as in "--flag=value", so it is the flag's value
+    /// even if it looks like a flag
+    attached: bool,
+}
+
+/// A flag found in a flag token by Parser::find_flags
+struct FoundFlag<'a> {
+    /// The flag as passed, e.g. "-f" from the cluster "-xvf"
+    flag: String,
+    config: &'a Rc<ArgConfig>,
+    /// The value given in the same token, e.g. "file" in "-ffile"
+    value: Option<Token>,
 }
 
 pub struct Parser {
-    description: String,
+    /// The name of the subcommand. Empty for the top level parser.
     command: String,
+    description: String,
     flag_configs: Vec<Rc<ArgConfig>>,
     /// Map a flag to an index in flagConfigs
     flag_map: HashMap<String, Rc<ArgConfig>>,
     /// Vector of positional arg configs
     pos_arg_configs: Vec<ArgConfig>,
-    /// name -> arg value
-    parsed_args: HashMap<String, Arg>,
+    /// Nested parsers selected by name, e.g. "add" in "tool remote add"
+    subcommands: Vec<Parser>,
+    /// Whether the hidden "--generate-completions <shell>" flag is accepted
+    completions_flag: bool,
+    /// (command, description) of example invocations
+    examples: Vec<(String, String)>,
+    /// Width the help screen is wrapped to. None is the terminal's width.
+    help_width: Option<usize>,
+    /// Sets of arguments checked together after parsing
+    groups: Vec<ArgGroup>,
+    /// Name of the flag whose value is the path of the config file
+    config_file_flag: Option<String>,
+    /// Config file read when the config file flag isn't passed
+    default_config_file: Option<PathBuf>,
+    /// Whether "@path" arguments are replaced with the contents of the file
+    response_files: bool,
 }
 
 /// Instantiate a new arg parser
 pub fn new(description: String) -> Parser {
     Parser {
-        description: description,
         command: "".to_string(),
+        description,
         flag_configs: vec![],
         pos_arg_configs: vec![],
         flag_map: HashMap::new(),
-        parsed_args: HashMap::new(),
+        subcommands: vec![],
+        completions_flag: false,
+        examples: vec![],
+        help_width: None,
+        groups: vec![],
+        config_file_flag: None,
+        default_config_file: None,
+        response_files: false,
     }
 }
 
 impl Parser {
     /// Add a flag_config to the list of configs
-    fn add_flag_config(&mut self, flag_config: ArgConfig) -> Result<(), ParserError> {
+    fn add_flag_config(&mut self, flag_config: ArgConfig) -> Result<(), Error> {
         if !utils::validate_flag_config(&flag_config) {
-            return Err(ParserError::FlagConfigError);
+            return Err(Error::new(ErrorKind::InvalidConfig).with_token(&flag_config.name));
         }
 
         let rc_flag_config: Rc<ArgConfig> = Rc::new(flag_config);
@@ -93,6 +321,11 @@ impl Parser {
             );
         }
 
+        // Add mapping from "--no-long_flag" to flag_config
+        if let Some(negated_flag) = rc_flag_config.negated_flag() {
+            self.flag_map.insert(negated_flag, rc_flag_config.clone());
+        }
+
         // Add mapping from short_flag to flag_config
         if let Some(short_flag) = &rc_flag_config.short_flag {
             self.flag_map.insert(
@@ -106,9 +339,15 @@ impl Parser {
     }
 
     /// Add a positional arg config to the list of configs
-    fn add_pos_arg_config(&mut self, arg_config: ArgConfig) -> Result<(), ParserError> {
+    fn add_pos_arg_config(&mut self, arg_config: ArgConfig) -> Result<(), Error> {
         if !utils::validate_arg_config(&arg_config) {
-            return Err(ParserError::ArgConfigError);
+            return Err(Error::new(ErrorKind::InvalidConfig).with_token(&arg_config.name));
+        }
+
+        // Only one variadic positional can be told apart from the others
+        let is_variadic = |config: &ArgConfig| config.action == Action::Append;
+        if is_variadic(&arg_config) && self.pos_arg_configs.iter().any(is_variadic) {
+            return Err(Error::new(ErrorKind::InvalidConfig).with_token(&arg_config.name));
         }
 
         self.pos_arg_configs.push(arg_config);
@@ -125,127 +364,492 @@ impl Parser {
         }
     }
 
-    /// Whether it's "-f=value", "-fvalue", or "-f value", it becomes ["-f", "value"]
-    fn split_short_flag(&self, long_flag: &str) -> Vec<String> {
-        // short flag with length < 3 is just the single char
-        if long_flag.len() < 3 {
-            vec![long_flag.to_owned()]
-        } else {
-            let (flag, arg) = long_flag.split_at(2);
-            let arg_val = if let Some(stripped_arg) = arg.strip_prefix('=') {
-                stripped_arg.to_owned()
-            } else {
-                arg.to_owned()
-            };
-
-            vec![flag.to_owned(), arg_val]
+    /// "-f=value" becomes ["-f", "value"]. Clusters such as "-abc" or
+    /// "-fvalue" are split by find_flags, which knows which flags take values.
+    fn split_short_flag(&self, short_flag: &str) -> Vec<String> {
+        match short_flag.split_once('=') {
+            Some((flag, arg)) if flag.chars().count() == 2 => {
+                vec![flag.to_owned(), arg.to_owned()]
+            }
+            _ => vec![short_flag.to_owned()],
         }
     }
 
     fn get_flag_type(&self, item: &str) -> FlagType {
-        if is_long_flag(&item) {
+        if is_long_flag(item) {
             FlagType::LongFlag
-        } else if is_short_flag(&item) {
+        } else if is_short_flag(item) {
             FlagType::ShortFlag
         } else {
             FlagType::PositionalArgument
         }
     }
 
-    /// Takes iterator of Strings and splits up --flag=<val> into separate strings
-    /// returning vector of strings or error.
+    /// Takes iterator of Strings and splits up --flag=<val> into separate tokens
+    /// returning vector of tokens or error. Each token remembers its argv index.
+    /// Response files are expanded first if enabled.
     fn tokenize_flag_arg_values(
         &self,
         input_args: impl Iterator<Item = String>,
-    ) -> Result<Vec<String>, ParserError> {
-        let mut intermediate_args: Vec<String> = vec![];
-        // Parse into intermediate format
-        for item in input_args {
+    ) -> Result<Vec<Token>, Error> {
+        let mut intermediate_args: Vec<Token> = vec![];
+        let mut options_ended = false;
+        // Parse into intermediate format. Index 0 is the command.
+        for (index, item) in self.expand_response_files(input_args)? {
+            // Nothing after "--" is a flag, so nothing is split
+            if options_ended {
+                intermediate_args.push(Token {
+                    value: item,
+                    index,
+                    attached: false,
+                });
+                continue;
+            }
+            options_ended = item == "--";
+
             let split_flag: Vec<String> = match self.get_flag_type(&item) {
                 FlagType::LongFlag => self.split_long_flag(&item),
                 FlagType::ShortFlag => self.split_short_flag(&item),
                 FlagType::PositionalArgument => vec![item],
             };
 
-            for val in split_flag {
-                intermediate_args.push(val);
+            for (i, value) in split_flag.into_iter().enumerate() {
+                intermediate_args.push(Token {
+                    value,
+                    index,
+                    attached: i > 0,
+                });
             }
         }
 
         Ok(intermediate_args)
     }
 
-    /// Parses a string representing the flag's argument value
+    /// Parses a token representing the argument value of the config
     /// and return it as an Arg of type defined by arg_type.
-    fn parse_flag_arg_value(&self, arg_type: &ArgType, arg: &str) -> Result<Arg, ParserError> {
-        if utils::is_flag(&arg) {
-            return Err(ParserError::ArgValueIsFlag);
+    fn parse_flag_arg_value(
+        &self,
+        config: &ArgConfig,
+        arg_type: &ArgType,
+        token: &Token,
+    ) -> Result<Arg, Error> {
+        let arg = token.value.as_str();
+        let error = |kind: ErrorKind| {
+            Error::new(kind)
+                .with_token(arg)
+                .with_index(token.index)
+                .with_arg(&config.display_name())
+        };
+
+        let value = arg_type
+            .value_parser()
+            .parse(arg)
+            .map_err(|detail| error(ErrorKind::IncorrectArgType).with_detail(detail))?;
+
+        match &config.range {
+            Some(range) if !range.contains(&value) => {
+                Err(error(ErrorKind::OutOfRange).with_detail(range.to_string()))
+            }
+            _ => Ok(value),
         }
+    }
 
-        match arg_type {
-            ArgType::Character => {
-                if arg.len() != 1 {
-                    return Err(ParserError::IncorrectArgType);
-                }
+    /// Parses the value of a flag, splitting it on the config's delimiter
+    /// if it has one, e.g. "a,b,c" becomes [a, b, c].
+    fn parse_flag_arg_values(
+        &self,
+        config: &ArgConfig,
+        arg_type: &ArgType,
+        token: &Token,
+    ) -> Result<Vec<Arg>, Error> {
+        let delimiter = match config.delimiter {
+            Some(delimiter) => delimiter,
+            None => return Ok(vec![self.parse_flag_arg_value(config, arg_type, token)?]),
+        };
+
+        token
+            .value
+            .split(delimiter)
+            .map(|value| {
+                let token = Token {
+                    value: value.to_owned(),
+                    ..*token
+                };
+                self.parse_flag_arg_value(config, arg_type, &token)
+            })
+            .collect()
+    }
+
+    /// Look up the configs of the flags in a flag token. "-abc" is the
+    /// cluster of short flags "-a -b -c". The first flag in a cluster that
+    /// takes a value takes the rest of the cluster as its value, e.g.
+    /// "-xvffile" is "-x -v -f file" and "-xvf" takes the next token.
+    fn find_flags<'a>(
+        &'a self,
+        item: &Token,
+        globals: &'a HashMap<String, Rc<ArgConfig>>,
+    ) -> Result<Vec<FoundFlag<'a>>, Error> {
+        let lookup = |flag: &str| {
+            self.flag_map
+                .get(flag)
+                .or_else(|| globals.get(flag))
+                .ok_or_else(|| {
+                    let candidates = self
+                        .flag_map
+                        .keys()
+                        .chain(globals.keys())
+                        .map(String::as_str)
+                        .chain(["--help"]);
+                    let error = Error::new(ErrorKind::UnknownFlag)
+                        .with_token(flag)
+                        .with_index(item.index);
+                    match suggest::did_you_mean(flag, candidates) {
+                        Some(suggestion) => error.with_detail(suggestion),
+                        None => error,
+                    }
+                })
+        };
+
+        if is_long_flag(&item.value) || item.value.chars().count() <= 2 {
+            return Ok(vec![FoundFlag {
+                flag: item.value.clone(),
+                config: lookup(&item.value)?,
+                value: None,
+            }]);
+        }
+
+        let mut flags = vec![];
+        for (i, c) in item.value.char_indices().skip(1) {
+            let flag = utils::add_dash_to_short_flag(c);
+            let config = lookup(&flag)?;
+            if config.arg_type.is_none() {
+                flags.push(FoundFlag {
+                    flag,
+                    config,
+                    value: None,
+                });
+                continue;
+            }
+
+            let rest = &item.value[i + c.len_utf8()..];
+            let rest = rest.strip_prefix('=').unwrap_or(rest);
+            let value = (!rest.is_empty()).then(|| Token {
+                value: rest.to_owned(),
+                index: item.index,
+                attached: true,
+            });
+            flags.push(FoundFlag {
+                flag,
+                config,
+                value,
+            });
+            break;
+        }
+        Ok(flags)
+    }
+
+    /// Whether the token after a flag taking a value can be its value. A
+    /// token that looks like a flag only is if it was attached to the flag
+    /// as in "--flag=-x", if it is a negative number for a numeric flag, or
+    /// if the flag allows hyphen values.
+    fn is_flag_value(
+        &self,
+        config: &ArgConfig,
+        token: &Token,
+        globals: &HashMap<String, Rc<ArgConfig>>,
+    ) -> bool {
+        !utils::is_flag(&token.value)
+            || token.attached
+            || config.allow_hyphen_values
+            || (config.arg_type.as_ref().is_some_and(ArgType::is_numeric)
+                && utils::is_negative_number(&token.value)
+                && !self.is_short_flag_defined(&token.value, globals))
+    }
+
+    /// Whether a token that looks like a flag is a negative number for a
+    /// numeric positional argument, e.g. "-1"
+    fn is_negative_positional(
+        &self,
+        token: &Token,
+        globals: &HashMap<String, Rc<ArgConfig>>,
+    ) -> bool {
+        let has_numeric_positional = self
+            .pos_arg_configs
+            .iter()
+            .any(|config| config.arg_type.as_ref().is_some_and(ArgType::is_numeric));
+        has_numeric_positional
+            && utils::is_negative_number(&token.value)
+            && !self.is_short_flag_defined(&token.value, globals)
+    }
 
-                // Unwrap is okay here because of the length check above
-                Ok(Arg::Character(arg.chars().next().unwrap()))
+    /// Whether the first short flag of a token such as "-5" is defined, in
+    /// which case the token is a flag rather than a negative number
+    fn is_short_flag_defined(&self, token: &str, globals: &HashMap<String, Rc<ArgConfig>>) -> bool {
+        match token.chars().nth(1) {
+            Some(c) => {
+                let flag = utils::add_dash_to_short_flag(c);
+                self.flag_map.contains_key(&flag) || globals.contains_key(&flag)
             }
-            ArgType::Float => match arg.parse::<f32>() {
-                Err(_) => Err(ParserError::IncorrectArgType),
-                Ok(val) => Ok(Arg::Float(val)),
-            },
-            ArgType::Integer => match arg.parse::<i32>() {
-                Err(_) => Err(ParserError::IncorrectArgType),
-                Ok(val) => Ok(Arg::Integer(val)),
-            },
-            ArgType::String => Ok(Arg::String(arg.to_owned())),
+            None => false,
         }
     }
 
     /// Check whether all required flags have been set.
     /// Set all default flag values in parsed_args if unset.
-    fn validate_flags(&mut self) -> Result<(), ParserError> {
+    /// Global flags defined by this parser are checked in global_matches instead.
+    fn validate_flags(
+        &self,
+        matches: &mut Matches,
+        global_matches: &mut Matches,
+        config: &ConfigValues,
+    ) -> Result<(), Error> {
         for item in &self.flag_configs {
-            if self.parsed_args.contains_key(&item.name) {
-                continue;
+            if item.global {
+                self.validate_arg(item, global_matches, config)?;
+            } else {
+                self.validate_arg(item, matches, config)?;
             }
+        }
+
+        for item in &self.pos_arg_configs {
+            self.validate_arg(item, matches, config)?;
+        }
+        Ok(())
+    }
 
-            if item.required {
-                return Err(ParserError::MissingRequiredFlag);
+    /// Check a single flag or positional argument was passed as required.
+    /// If it wasn't passed, fall back to its environment variable, the
+    /// config file and then its default value, recording where the value
+    /// came from.
+    fn validate_arg(
+        &self,
+        item: &ArgConfig,
+        matches: &mut Matches,
+        config: &ConfigValues,
+    ) -> Result<(), Error> {
+        matches
+            .declared_types
+            .insert(item.name.clone(), item.declared_type());
+        let too_few = || Error::new(ErrorKind::TooFewOccurrences).with_token(&item.display_name());
+
+        if matches.parsed_args.contains_key(&item.name) {
+            if matches.occurrences(&item.name) < item.min_occurrences {
+                return Err(too_few());
             }
+            matches
+                .sources
+                .insert(item.name.clone(), ValueSource::CommandLine);
+            return Ok(());
+        }
 
-            // For non-required fields, set their arg values
-            match item.arg_type {
-                Some(_) => {
-                    self.parsed_args.insert(item.name.clone(), Arg::None);
-                }
-                None => {
-                    self.parsed_args
-                        .insert(item.name.clone(), Arg::Boolean(false));
+        if let Some(arg) = self.parse_env_value(item)? {
+            matches.parsed_args.insert(item.name.clone(), arg);
+            matches
+                .sources
+                .insert(item.name.clone(), ValueSource::Environment);
+            return Ok(());
+        }
+
+        if let Some(arg) = config.values.get(&item.name) {
+            matches.parsed_args.insert(item.name.clone(), arg.clone());
+            matches
+                .sources
+                .insert(item.name.clone(), ValueSource::ConfigFile);
+            return Ok(());
+        }
+
+        if item.required {
+            return Err(Error::new(ErrorKind::MissingRequired).with_token(&item.display_name()));
+        }
+
+        if let Some(default_value) = &item.default_value {
+            matches
+                .parsed_args
+                .insert(item.name.clone(), default_value.clone());
+            matches
+                .sources
+                .insert(item.name.clone(), ValueSource::Default);
+            return Ok(());
+        }
+
+        // Nothing supplied the occurrences the arg needs
+        if item.min_occurrences > 0 {
+            return Err(too_few());
+        }
+
+        // For non-required fields, set their arg values
+        let arg = match (&item.action, &item.arg_type) {
+            (Action::Append, _) => Arg::List(vec![]),
+            (Action::Count, _) => Arg::Unsigned64(0),
+            (Action::Set, Some(_)) => Arg::None,
+            (Action::Set, None) => Arg::Boolean(false),
+        };
+        matches.parsed_args.insert(item.name.clone(), arg);
+        Ok(())
+    }
+
+    /// Read and parse the value of the config's environment variable.
+    /// Returns None if it has no environment variable or it is unset.
+    fn parse_env_value(&self, item: &ArgConfig) -> Result<Option<Arg>, Error> {
+        let env_name = match &item.env {
+            Some(env_name) => env_name,
+            None => return Ok(None),
+        };
+        let env_value = match env::var(env_name) {
+            Ok(env_value) => env_value,
+            Err(_) => return Ok(None),
+        };
+        let env_name = format!("${}", env_name);
+        let error = || {
+            Error::new(ErrorKind::IncorrectArgType)
+                .with_token(&env_value)
+                .with_arg(&env_name)
+        };
+
+        let token = Token {
+            value: env_value.clone(),
+            index: 0,
+            attached: true,
+        };
+        let arg = match &item.arg_type {
+            // Counted flags take the count
+            None if item.action == Action::Count => {
+                Arg::Unsigned64(env_value.parse::<u64>().map_err(|_| error())?)
+            }
+            // Boolean flags accept the usual spellings of true and false
+            None => Arg::Boolean(utils::parse_bool(&env_value).ok_or_else(error)?),
+            Some(arg_type) => {
+                // Keep the kind and detail of the error, e.g. the range
+                let mut values =
+                    self.parse_flag_arg_values(item, arg_type, &token)
+                        .map_err(|e| {
+                            let env_error = Error::new(e.kind())
+                                .with_token(&env_value)
+                                .with_arg(&env_name);
+                            match e.detail() {
+                                Some(detail) => env_error.with_detail(detail.to_string()),
+                                None => env_error,
+                            }
+                        })?;
+                match item.action {
+                    // Counted flags have no arg_type, so they never get here
+                    Action::Set | Action::Count => values.remove(0),
+                    Action::Append => Arg::List(values),
                 }
             }
+        };
+
+        Ok(Some(arg))
+    }
+
+    /// Assign the positional tokens to the positional arg configs in order.
+    /// A variadic (Action::Append) positional takes whatever is left after
+    /// the positionals before and after it, e.g. "cp SRC... DST".
+    fn assign_positionals(
+        &self,
+        pos_tokens: Vec<Token>,
+        matches: &mut Matches,
+    ) -> Result<(), Error> {
+        let variadic_index = self
+            .pos_arg_configs
+            .iter()
+            .position(|config| config.action == Action::Append);
+
+        // Positionals before the variadic get the first tokens, positionals
+        // after it get the last tokens and the variadic gets the rest.
+        let (leading, trailing) = match variadic_index {
+            Some(index) => self.pos_arg_configs.split_at(index),
+            None => (&self.pos_arg_configs[..], &[][..]),
+        };
+        let trailing = trailing.get(1..).unwrap_or_default();
+        let leading_count = leading.len().min(pos_tokens.len());
+        let trailing_count = trailing.len().min(pos_tokens.len() - leading_count);
+        let variadic_end = pos_tokens.len() - trailing_count;
+
+        let mut pos_tokens = pos_tokens.into_iter();
+        let mut assigned: Vec<(&ArgConfig, Token)> = vec![];
+        for config in leading.iter().take(leading_count) {
+            // Unwrap is safe here because of the counts above
+            assigned.push((config, pos_tokens.next().unwrap()));
         }
 
-        for item in &self.pos_arg_configs {
-            if self.parsed_args.contains_key(&item.name) {
-                continue;
+        let variadic_tokens: Vec<Token> = pos_tokens
+            .by_ref()
+            .take(variadic_end - leading_count)
+            .collect();
+        for (config, token) in trailing.iter().zip(pos_tokens) {
+            assigned.push((config, token));
+        }
+
+        match variadic_index.map(|index| &self.pos_arg_configs[index]) {
+            Some(config) => {
+                if let Some(token) = config
+                    .max_occurrences
+                    .and_then(|max| variadic_tokens.get(max))
+                {
+                    return Err(Error::new(ErrorKind::TooManyPositionals)
+                        .with_token(&token.value)
+                        .with_index(token.index));
+                }
+
+                matches
+                    .occurrences
+                    .insert(config.name.clone(), variadic_tokens.len());
+                if !variadic_tokens.is_empty() {
+                    let mut list = vec![];
+                    for token in &variadic_tokens {
+                        list.extend(self.parse_pos_arg_values(config, token)?);
+                    }
+                    matches
+                        .parsed_args
+                        .insert(config.name.clone(), Arg::List(list));
+                }
             }
+            None => {
+                // Without a variadic every token beyond the configs is extra
+                if let Some(token) = variadic_tokens.first() {
+                    if self.subcommands.is_empty() {
+                        return Err(Error::new(ErrorKind::TooManyPositionals)
+                            .with_token(&token.value)
+                            .with_index(token.index));
+                    }
 
-            if item.required {
-                return Err(ParserError::MissingRequiredFlag);
+                    let error = Error::new(ErrorKind::UnknownSubcommand)
+                        .with_token(&token.value)
+                        .with_index(token.index);
+                    let names = self.subcommands.iter().map(|sub| sub.command.as_str());
+                    return Err(match suggest::did_you_mean(&token.value, names) {
+                        Some(suggestion) => error.with_detail(suggestion),
+                        None => error,
+                    });
+                }
             }
+        }
 
-            self.parsed_args.insert(item.name.clone(), Arg::None);
+        for (config, token) in assigned {
+            let mut values = self.parse_pos_arg_values(config, &token)?;
+            matches.occurrences.insert(config.name.clone(), 1);
+            matches
+                .parsed_args
+                .insert(config.name.clone(), values.remove(0));
         }
+
         Ok(())
     }
+
+    /// Parse the value of a positional argument
+    fn parse_pos_arg_values(&self, config: &ArgConfig, token: &Token) -> Result<Vec<Arg>, Error> {
+        // Unwrap is safe here because we validate upon insertion
+        // that positional args have an arg_type != None
+        let arg_type = config.arg_type.as_ref().unwrap();
+        self.parse_flag_arg_values(config, arg_type, token)
+    }
 }
 
 impl Parser {
     /// Define a flag configuration
     pub fn add_flag(
-        mut self,
+        self,
         name: String,
         long_flag: Option<String>,
         short_flag: Option<char>,
@@ -253,212 +857,389 @@ impl Parser {
         arg_type: Option<ArgType>,
         description: String,
     ) -> Self {
-        let flag_config = ArgConfig {
-            name: name,
-            short_flag: short_flag,
-            long_flag: long_flag,
-            required: required,
-            arg_type: arg_type,
-            description: description,
-        };
-        // Unwrap here is okay because this indicates a bug in the
-        // configuration, not due to end user inputing incorrect args
-        if let (None, None) = (&flag_config.long_flag, &flag_config.short_flag) {
-            self.add_pos_arg_config(flag_config).unwrap();
-        } else {
-            self.add_flag_config(flag_config).unwrap();
-        }
+        let mut flag_config = ArgConfig::new(name).required(required);
+        flag_config.long_flag = long_flag;
+        flag_config.short_flag = short_flag;
+        flag_config.arg_type = arg_type;
+        flag_config.description = description;
 
-        self
+        self.add_arg(flag_config)
     }
 
-    /// Print the help screen
-    //TODO: Print argument type correctly
-    pub fn print_help(&self) {
-        let mut help_output = format!("\n{}\n\n", &self.description);
-        help_output.push_str("usage: COMMAND [options] ");
+    /// Define a flag that is accepted by this parser and all of its
+    /// subcommands, e.g. "tool --verbose remote add" or "tool remote add --verbose".
+    pub fn add_global_flag(
+        self,
+        name: String,
+        long_flag: Option<String>,
+        short_flag: Option<char>,
+        required: bool,
+        arg_type: Option<ArgType>,
+        description: String,
+    ) -> Self {
+        let mut flag_config = ArgConfig::new(name).required(required).global(true);
+        flag_config.long_flag = long_flag;
+        flag_config.short_flag = short_flag;
+        flag_config.arg_type = arg_type;
+        flag_config.description = description;
 
-        for flag in &self.flag_configs {
-            if !flag.required {
-                continue;
-            }
+        self.add_arg(flag_config)
+    }
 
-            if let Some(long_flag) = &flag.long_flag {
-                help_output.push_str(&format!(
-                    "--{}=<{}:{:?}> ",
-                    long_flag, &flag.name, &flag.arg_type
-                ));
-            } else if let Some(short_flag) = &flag.short_flag {
-                help_output.push_str(&format!(
-                    "-{}=<{}:{:?}> ",
-                    short_flag, &flag.name, &flag.arg_type
-                ));
-            }
+    /// Define a flag or positional argument from an ArgConfig. This allows
+    /// settings that add_flag doesn't expose, such as Action::Append.
+    pub fn add_arg(mut self, arg_config: ArgConfig) -> Self {
+        // Unwrap here is okay because this indicates a bug in the
+        // configuration, not due to end user inputing incorrect args
+        if arg_config.is_positional() {
+            self.add_pos_arg_config(arg_config).unwrap();
+        } else {
+            self.add_flag_config(arg_config).unwrap();
         }
 
-        for arg in &self.pos_arg_configs {
-            if arg.required {
-                help_output.push_str(&format!("<{}:{:?}> ", &arg.name, &arg.arg_type));
-            } else {
-                help_output.push_str(&format!("[<{}:{:?}>] ", &arg.name, &arg.arg_type));
-            }
-        }
+        self
+    }
 
-        help_output.push_str("\n\nFlags:\n");
+    /// Define a subcommand, e.g. "add" in "tool remote add --name x url".
+    /// The subcommand is its own parser with its own flags, positional
+    /// arguments, description and help screen. Subcommands can be nested.
+    pub fn add_subcommand(mut self, name: String, mut subcommand: Parser) -> Self {
+        subcommand.command = name;
+        self.subcommands.push(subcommand);
+        self
+    }
 
-        help_output.push_str(&format!("\n\t--help "));
-        help_output.push_str(&format!("\n\t\tPrint this help page\n"));
+    /// Print the long help screen
+    pub fn print_help(&self) {
+        let command = env::args().next().unwrap_or_default();
+        println!(
+            "{}",
+            self.render_help(help::program_name(&command), HelpStyle::Long)
+        );
+    }
 
-        for item in &self.flag_configs {
-            if let Some(flag) = item.short_flag {
-                help_output.push_str(&format!("\n\t-{} ", flag));
-                if let Some(arg_type) = &item.arg_type {
-                    help_output.push_str(&format!("<{}:{:?}> ", &item.name, arg_type));
-                }
-            }
-            if let Some(flag) = &item.long_flag {
-                help_output.push_str(&format!("\n\t--{} ", flag));
-                if let Some(_arg_type) = &item.arg_type {
-                    help_output.push_str(&format!("<{}:{:?}> ", &item.name, &item.arg_type));
-                }
-            }
-            if item.required {
-                help_output.push_str("\n\t\t(required) ");
+    /// Parse the command line arguments, printing the error and help screen
+    /// to stderr and exiting the process with status 2 if they are invalid.
+    ///
+    /// See try_parse for the accepted syntax.
+    pub fn parse(&self, input_args: impl Iterator<Item = String>) -> Matches {
+        match self.try_parse(input_args) {
+            Ok(matches) => matches,
+            Err(e)
+                if e.kind() == ErrorKind::HelpRequested
+                    || e.kind() == ErrorKind::CompletionsRequested =>
+            {
+                println!("{}", e.help().unwrap_or_default());
+                exit(0);
             }
-            help_output.push_str(&format!("\n\t\t{}", &item.description));
-            help_output.push('\n');
-        }
-
-        help_output.push_str("\nPositional Arguments:\n");
-        for item in &self.pos_arg_configs {
-            help_output.push_str(&format!("\n\t{}", &item.name));
-            if item.required {
-                help_output.push_str("\n\t\t(required)");
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                eprintln!("{}", e.help().unwrap_or_default());
+                exit(2);
             }
-            help_output.push_str(&format!("\n\t\t{}", &item.description));
-            help_output.push('\n');
         }
-
-        println!("{}", help_output);
     }
 
     /// Parse the command line arguments
     ///
     /// - Short flag: -f=<arg> | -f <arg> | -f<arg>
+    /// - Short flag cluster: -abc | -abf <arg> | -abf<arg>
     /// - Long flag: --flag=<arg> | --flag <arg>
-    // TODO: Simplify this function. It is too complicated.
-    pub fn parse(mut self, mut input_args: impl Iterator<Item = String>) -> Self {
+    /// - End of flags: -- <arg>... (even if the args start with "-")
+    /// - Response file: @path (if enabled with response_files)
+    /// - Subcommand: COMMAND [options] <subcommand> [subcommand options]
+    ///
+    /// Args that aren't passed take their value from their environment
+    /// variable, then the config file (see config_file_flag) and then their
+    /// default value.
+    pub fn try_parse(
+        &self,
+        mut input_args: impl Iterator<Item = String>,
+    ) -> Result<Matches, Error> {
         // There must be at least one arg and the first one is the command
-        self.command = if let Some(arg) = input_args.next() {
-            arg
-        } else {
-            println!("Must have more than one argument (the command)");
-            self.print_help();
-            exit(-1);
+        let mut matches = match input_args.next() {
+            Some(command) => Matches::new(command),
+            None => {
+                return Err(Error::new(ErrorKind::MissingCommand)
+                    .with_help(self.render_help("COMMAND", HelpStyle::Short)))
+            }
         };
 
         // Tokenize all the flag argument values:
         // e.g. "--flag=value" turns into ["--flag", "value"]
-        let mut tokenized_args = if let Ok(tokens) = self.tokenize_flag_arg_values(input_args) {
-            tokens.into_iter()
-        } else {
-            println!("Problem tokenizing args");
-            self.print_help();
-            exit(-1);
-        };
+        let tokenized_args = self.tokenize_flag_arg_values(input_args)?;
+        let command_path = help::program_name(&matches.command).to_string();
+
+        // Values from the config file are used for flags that aren't passed
+        let config = self
+            .load_config(&tokenized_args)
+            .map_err(|e| e.with_help(self.render_help(&command_path, HelpStyle::Short)))?;
+        let mut tokenized_args = tokenized_args.into_iter();
+
+        // Values of global flags are shared by every level of subcommands
+        let mut global_matches = Matches::new(matches.command.clone());
+        self.parse_subcommand(
+            &mut tokenized_args,
+            &command_path,
+            &HashMap::new(),
+            &config,
+            &mut matches,
+            &mut global_matches,
+        )?;
+        self.apply_global_args(&mut matches, &[], &global_matches);
+
+        Ok(matches)
+    }
 
+    /// Parse tokens for this parser, descending into a subcommand if one is
+    /// named. globals holds the global flags defined by the parent parsers.
+    // TODO: Simplify this function. It is too complicated.
+    fn parse_subcommand(
+        &self,
+        tokenized_args: &mut impl Iterator<Item = Token>,
+        command_path: &str,
+        globals: &HashMap<String, Rc<ArgConfig>>,
+        config: &ConfigValues,
+        matches: &mut Matches,
+        global_matches: &mut Matches,
+    ) -> Result<(), Error> {
         // Since positional args don't have flags, they can't be mapped to
-        // specific configs. To track which args map to which config, we
-        // consume the configs as an iteratator as we parse.
-        let mut pos_args_iter = self.pos_arg_configs.iter();
+        // specific configs until we know how many there are. Collect them
+        // and assign them to the configs after parsing the flags.
+        let mut pos_tokens: Vec<Token> = vec![];
+
+        let h_taken = globals.contains_key("-h");
+        let help_text = |style| self.render_help_for(command_path, style, h_taken);
 
         // Main parsing loop
         while let Some(item) = tokenized_args.next() {
-            if &item == "--help" {
-                self.print_help();
-                exit(0);
+            let help_style = match item.value.as_str() {
+                "--help" => Some(HelpStyle::Long),
+                "-h" if self.has_short_help_flag(h_taken) => Some(HelpStyle::Short),
+                _ => None,
+            };
+            if let Some(style) = help_style {
+                return Err(Error::new(ErrorKind::HelpRequested)
+                    .with_token(&item.value)
+                    .with_index(item.index)
+                    .with_help(help_text(style)));
             }
 
-            if utils::is_flag(&item) {
-                let flag_config = if let Some(flag_config) = self.flag_map.get(&item) {
-                    flag_config
-                } else {
-                    println!("Flag not defined");
-                    self.print_help();
-                    exit(-1);
+            if self.completions_flag && item.value == "--generate-completions" {
+                let shell = match tokenized_args.next() {
+                    Some(shell) => shell.value.parse::<Shell>().map_err(|detail| {
+                        Error::new(ErrorKind::IncorrectArgType)
+                            .with_token(&shell.value)
+                            .with_index(shell.index)
+                            .with_arg(&item.value)
+                            .with_detail(detail)
+                    })?,
+                    None => {
+                        return Err(Error::new(ErrorKind::MissingValue)
+                            .with_token(&item.value)
+                            .with_index(item.index))
+                    }
                 };
 
-                let parsed_arg = match &flag_config.arg_type {
-                    // None implies boolean
-                    None => Arg::Boolean(true),
-                    // Having an arg_type means we need to parse the next arg
-                    Some(arg_type) => {
-                        let next_arg = match tokenized_args.next() {
-                            None => {
-                                println!("expected argument");
-                                self.print_help();
-                                exit(-1);
-                            }
-                            Some(arg) => arg,
-                        };
-
-                        match self.parse_flag_arg_value(arg_type, &next_arg) {
-                            Ok(val) => val,
-                            Err(e) => {
-                                println!("{:?}", e);
-                                self.print_help();
-                                exit(-1);
-                            }
+                // Only the top level parser has the flag, so command_path
+                // is the program name
+                return Err(Error::new(ErrorKind::CompletionsRequested)
+                    .with_token(&item.value)
+                    .with_index(item.index)
+                    .with_help(self.completions(shell, command_path)));
+            }
+
+            if item.value == "--" {
+                // Everything after "--" is a positional argument, even if
+                // it looks like a flag or names a subcommand
+                pos_tokens.extend(&mut *tokenized_args);
+                break;
+            }
+
+            if utils::is_flag(&item.value) && !self.is_negative_positional(&item, globals) {
+                let flags = self
+                    .find_flags(&item, globals)
+                    .map_err(|e| e.with_help(help_text(HelpStyle::Short)))?;
+
+                for FoundFlag {
+                    flag,
+                    config: flag_config,
+                    value: inline_value,
+                } in flags
+                {
+                    let parsed_args = match &flag_config.arg_type {
+                        // None implies boolean, false for the "--no-" form
+                        None => vec![Arg::Boolean(
+                            flag_config.negated_flag().as_ref() != Some(&flag),
+                        )],
+                        // Having an arg_type means we need to parse the rest
+                        // of the cluster or the next arg
+                        Some(arg_type) => {
+                            let value = match inline_value {
+                                Some(value) => value,
+                                None => match tokenized_args.next() {
+                                    Some(next_arg)
+                                        if !self.is_flag_value(flag_config, &next_arg, globals) =>
+                                    {
+                                        return Err(Error::new(ErrorKind::ArgValueIsFlag)
+                                            .with_token(&next_arg.value)
+                                            .with_index(next_arg.index)
+                                            .with_arg(&flag_config.display_name())
+                                            .with_help(help_text(HelpStyle::Short)))
+                                    }
+                                    Some(next_arg) => next_arg,
+                                    None => {
+                                        return Err(Error::new(ErrorKind::MissingValue)
+                                            .with_token(&flag)
+                                            .with_index(item.index)
+                                            .with_help(help_text(HelpStyle::Short)))
+                                    }
+                                },
+                            };
+                            self.parse_flag_arg_values(flag_config, arg_type, &value)
+                                .map_err(|e| e.with_help(help_text(HelpStyle::Short)))?
                         }
+                    };
+
+                    let matches = if flag_config.global {
+                        &mut *global_matches
+                    } else {
+                        &mut *matches
+                    };
+
+                    let occurrences = matches
+                        .occurrences
+                        .entry(flag_config.name.clone())
+                        .or_insert(0);
+                    *occurrences += 1;
+                    let count = *occurrences;
+                    if flag_config
+                        .max_occurrences
+                        .is_some_and(|max| *occurrences > max)
+                    {
+                        return Err(Error::new(ErrorKind::TooManyOccurrences)
+                            .with_token(&flag)
+                            .with_index(item.index)
+                            .with_help(help_text(HelpStyle::Short)));
                     }
-                };
 
-                self.parsed_args
-                    .insert(flag_config.name.clone(), parsed_arg);
-            } else {
-                // Parse positional argument
-                if let Some(flag_config) = pos_args_iter.next() {
-                    // Unwrap is safe here because we validate upon insertion
-                    // that positional args have an arg_type != None
-                    let arg_type = flag_config.arg_type.as_ref().unwrap();
-                    let arg_value = match self.parse_flag_arg_value(arg_type, &item) {
-                        Ok(val) => val,
-                        Err(e) => {
-                            println!("{:?}", e);
-                            self.print_help();
-                            exit(-1);
+                    match flag_config.action {
+                        Action::Set => {
+                            // Set flags never split their value so there is exactly one
+                            let parsed_arg = parsed_args.into_iter().next().unwrap_or(Arg::None);
+                            matches
+                                .parsed_args
+                                .insert(flag_config.name.clone(), parsed_arg);
                         }
-                    };
-                    self.parsed_args.insert(flag_config.name.clone(), arg_value);
-                } else {
-                    println!("got more positional arguements than configured");
-                    self.print_help();
-                    exit(-1);
+                        Action::Append => {
+                            let list = matches
+                                .parsed_args
+                                .entry(flag_config.name.clone())
+                                .or_insert_with(|| Arg::List(vec![]));
+                            if let Arg::List(list) = list {
+                                list.extend(parsed_args);
+                            }
+                        }
+                        Action::Count => {
+                            matches
+                                .parsed_args
+                                .insert(flag_config.name.clone(), Arg::Unsigned64(count as u64));
+                        }
+                    }
+                }
+            } else if let Some(subcommand) = self.find_subcommand(&item.value) {
+                // Only a token in the position of the first positional
+                // argument can name a subcommand
+                if !pos_tokens.is_empty() {
+                    return Err(Error::new(ErrorKind::TooManyPositionals)
+                        .with_token(&item.value)
+                        .with_index(item.index)
+                        .with_help(help_text(HelpStyle::Short)));
+                }
+
+                // Global flags of this parser are visible to the subcommand
+                let mut sub_globals = globals.clone();
+                for (flag, flag_config) in &self.flag_map {
+                    if flag_config.global {
+                        sub_globals.insert(flag.clone(), flag_config.clone());
+                    }
                 }
+
+                let no_config = ConfigValues::default();
+                let sub_config = config
+                    .subcommands
+                    .get(&subcommand.command)
+                    .unwrap_or(&no_config);
+                let mut sub_matches = Matches::new(subcommand.command.clone());
+                subcommand.parse_subcommand(
+                    tokenized_args,
+                    &format!("{} {}", command_path, subcommand.command),
+                    &sub_globals,
+                    sub_config,
+                    &mut sub_matches,
+                    global_matches,
+                )?;
+                matches.subcommand = Some(Box::new(sub_matches));
+            } else {
+                pos_tokens.push(item);
             }
         }
 
-        if let Err(e) = self.validate_flags() {
-            println!("Error: {:?}", e);
-            self.print_help();
-            exit(-1);
+        self.assign_positionals(pos_tokens, matches)
+            .map_err(|e| e.with_help(help_text(HelpStyle::Short)))?;
+        self.validate_flags(matches, global_matches, config)
+            .and_then(|_| self.validate_relationships(globals, matches, global_matches))
+            .map_err(|e| e.with_help(help_text(HelpStyle::Short)))
+    }
+
+    /// Copy the values of global flags into the matches of every
+    /// subcommand level they are visible to.
+    fn apply_global_args(
+        &self,
+        matches: &mut Matches,
+        parent_globals: &[&str],
+        global_matches: &Matches,
+    ) {
+        let mut globals = parent_globals.to_vec();
+        for flag_config in &self.flag_configs {
+            if flag_config.global {
+                globals.push(&flag_config.name);
+            }
         }
 
-        self
+        for name in &globals {
+            if let Some(arg) = global_matches.parsed_args.get(*name) {
+                matches.parsed_args.insert(name.to_string(), arg.clone());
+            }
+            if let Some(occurrences) = global_matches.occurrences.get(*name) {
+                matches.occurrences.insert(name.to_string(), *occurrences);
+            }
+            if let Some(source) = global_matches.sources.get(*name) {
+                matches.sources.insert(name.to_string(), *source);
+            }
+            if let Some(declared_type) = global_matches.declared_types.get(*name) {
+                matches
+                    .declared_types
+                    .insert(name.to_string(), declared_type.clone());
+            }
+        }
+
+        if let Some(sub_matches) = matches.subcommand.as_deref_mut() {
+            if let Some(subcommand) = self.find_subcommand(&sub_matches.command) {
+                subcommand.apply_global_args(sub_matches, &globals, global_matches);
+            }
+        }
     }
 
-    /// Retrieve the parsed arg value
-    pub fn get_arg(&self, name: &str) -> Option<&Arg> {
-        self.parsed_args.get(name)
+    fn find_subcommand(&self, name: &str) -> Option<&Parser> {
+        self.subcommands.iter().find(|sub| sub.command == name)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    // TODO: Test missing required flags are returning errors appropriately
-    // TODO: Test incorrect arg type are returning errors appropriately
+    use std::net::IpAddr;
 
     // Test missing non-required flags are set appropriately
     #[test]
@@ -815,6 +1596,1621 @@ mod tests {
         }
     }
 
+    /// Verify missing required flags and positional args are returned as errors
+    #[test]
+    fn test_missing_required() {
+        let parser = new("test parser".to_string())
+            .add_flag(
+                "f_flag".to_string(),
+                Some("f_flag".to_string()),
+                None,
+                true,
+                Some(ArgType::String),
+                "Required flag".to_string(),
+            )
+            .add_flag(
+                "pos".to_string(),
+                None,
+                None,
+                true,
+                Some(ArgType::String),
+                "Required positional".to_string(),
+            );
+
+        let args = vec!["command".to_string(), "pos_val".to_string()];
+        let e = parser.try_parse(args.into_iter()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::MissingRequired);
+        assert_eq!(e.token(), Some("--f_flag"));
+
+        let args = vec!["command".to_string(), "--f_flag=x".to_string()];
+        let e = parser.try_parse(args.into_iter()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::MissingRequired);
+        assert_eq!(e.token(), Some("<pos>"));
+    }
+
+    /// Verify each kind of bad input is reported with its token and argv index
+    #[test]
+    fn test_try_parse_errors() {
+        let parser = new("test parser".to_string())
+            .add_flag(
+                "integer".to_string(),
+                Some("integer".to_string()),
+                Some('i'),
+                false,
+                Some(ArgType::Integer),
+                "Test an Integer".to_string(),
+            )
+            .add_flag(
+                "pos".to_string(),
+                None,
+                None,
+                false,
+                Some(ArgType::String),
+                "Test positional".to_string(),
+            );
+
+        let parse = |args: &[&str]| {
+            parser
+                .try_parse(args.iter().map(|arg| arg.to_string()))
+                .unwrap_err()
+        };
+
+        let e = parse(&["command", "pos", "--integer=abc"]);
+        assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+        assert_eq!(e.token(), Some("abc"));
+        assert_eq!(e.index(), Some(2));
+        assert_eq!(e.arg(), Some("--integer"));
+
+        let e = parse(&["command", "--unknown"]);
+        assert_eq!(e.kind(), ErrorKind::UnknownFlag);
+        assert_eq!(e.token(), Some("--unknown"));
+        assert_eq!(e.index(), Some(1));
+
+        let e = parse(&["command", "-i", "--integer"]);
+        assert_eq!(e.kind(), ErrorKind::ArgValueIsFlag);
+        assert_eq!(e.token(), Some("--integer"));
+        assert_eq!(e.index(), Some(2));
+
+        let e = parse(&["command", "pos1", "pos2"]);
+        assert_eq!(e.kind(), ErrorKind::TooManyPositionals);
+        assert_eq!(e.token(), Some("pos2"));
+        assert_eq!(e.index(), Some(2));
+
+        let e = parse(&["command", "--integer"]);
+        assert_eq!(e.kind(), ErrorKind::MissingValue);
+
+        let e = parse(&["command", "--help"]);
+        assert_eq!(e.kind(), ErrorKind::HelpRequested);
+
+        let e = parse(&[]);
+        assert_eq!(e.kind(), ErrorKind::MissingCommand);
+        assert_eq!(
+            e.to_string(),
+            "must have at least one argument (the command)"
+        );
+    }
+
+    /// Verify nested subcommands get their own flags and positional args,
+    /// and global flags are accepted at any level
+    #[test]
+    fn test_subcommands() {
+        let parser = new("test parser".to_string())
+            .add_global_flag(
+                "verbose".to_string(),
+                Some("verbose".to_string()),
+                Some('v'),
+                false,
+                None,
+                "Flag verbose".to_string(),
+            )
+            .add_subcommand(
+                "remote".to_string(),
+                new("Manage remotes".to_string()).add_subcommand(
+                    "add".to_string(),
+                    new("Add a remote".to_string())
+                        .add_flag(
+                            "name".to_string(),
+                            Some("name".to_string()),
+                            None,
+                            true,
+                            Some(ArgType::String),
+                            "Name of the remote".to_string(),
+                        )
+                        .add_flag(
+                            "url".to_string(),
+                            None,
+                            None,
+                            true,
+                            Some(ArgType::String),
+                            "URL of the remote".to_string(),
+                        ),
+                ),
+            );
+
+        let args = vec!["command", "remote", "add", "--name", "x", "-v", "url_val"];
+        let p = parser
+            .try_parse(args.into_iter().map(|arg| arg.to_string()))
+            .unwrap();
+
+        assert_eq!(p.subcommand_path(), vec!["remote", "add"]);
+        assert_eq!(p.get_arg("verbose"), Some(&Arg::Boolean(true)));
+        let (name, remote) = p.subcommand().unwrap();
+        assert_eq!(name, "remote");
+        assert_eq!(remote.get_arg("verbose"), Some(&Arg::Boolean(true)));
+        let (name, add) = remote.subcommand().unwrap();
+        assert_eq!(name, "add");
+        assert_eq!(add.get_arg("name"), Some(&Arg::String("x".to_string())));
+        assert_eq!(
+            add.get_arg("url"),
+            Some(&Arg::String("url_val".to_string()))
+        );
+        assert_eq!(add.get_arg("verbose"), Some(&Arg::Boolean(true)));
+
+        let args = vec!["command", "remote"];
+        let p = parser
+            .try_parse(args.into_iter().map(|arg| arg.to_string()))
+            .unwrap();
+        assert_eq!(p.subcommand_path(), vec!["remote"]);
+        assert_eq!(p.get_arg("verbose"), Some(&Arg::Boolean(false)));
+
+        let args = vec!["command", "remote", "rm"];
+        let e = parser
+            .try_parse(args.into_iter().map(|arg| arg.to_string()))
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnknownSubcommand);
+        assert_eq!(e.token(), Some("rm"));
+
+        // Help and errors describe the subcommand being parsed
+        let args = vec!["command", "remote", "add", "--help"];
+        let e = parser
+            .try_parse(args.into_iter().map(|arg| arg.to_string()))
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::HelpRequested);
+        assert!(e.help().unwrap().contains("Usage: command remote add"));
+        assert!(e.help().unwrap().contains("Add a remote"));
+
+        let args = vec!["command", "remote", "add", "url_val"];
+        let e = parser
+            .try_parse(args.into_iter().map(|arg| arg.to_string()))
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::MissingRequired);
+        assert!(e.help().unwrap().contains("Add a remote"));
+    }
+
+    /// Verify append flags collect every occurrence and split on delimiters
+    #[test]
+    fn test_append_flags() {
+        let parser = new("test parser".to_string())
+            .add_arg(
+                ArgConfig::new("include".to_string())
+                    .short_flag('I')
+                    .arg_type(ArgType::String)
+                    .action(Action::Append)
+                    .max_occurrences(3),
+            )
+            .add_arg(
+                ArgConfig::new("tags".to_string())
+                    .long_flag("tags".to_string())
+                    .arg_type(ArgType::Integer)
+                    .action(Action::Append)
+                    .delimiter(',')
+                    .min_occurrences(1),
+            );
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        let p = parse(&["command", "-I", "a", "-Ib", "--tags=1,2", "--tags", "3"]).unwrap();
+        assert_eq!(
+            p.get_arg("include"),
+            Some(&Arg::List(vec![
+                Arg::String("a".to_string()),
+                Arg::String("b".to_string())
+            ]))
+        );
+        assert_eq!(
+            p.get_arg("tags"),
+            Some(&Arg::List(vec![
+                Arg::Integer(1),
+                Arg::Integer(2),
+                Arg::Integer(3)
+            ]))
+        );
+        assert_eq!(p.occurrences("include"), 2);
+        assert_eq!(p.occurrences("tags"), 2);
+
+        let e = parse(&["command", "-Ia", "-Ib"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TooFewOccurrences);
+        assert_eq!(e.token(), Some("--tags"));
+
+        let e = parse(&["command", "--tags=1", "-Ia", "-Ib", "-Ic", "-Id"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TooManyOccurrences);
+        assert_eq!(e.index(), Some(5));
+
+        let e = parse(&["command", "--tags=1,x"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+        assert_eq!(e.token(), Some("x"));
+
+        let p = parse(&["command", "--tags=1"]).unwrap();
+        assert_eq!(p.get_arg("include"), Some(&Arg::List(vec![])));
+    }
+
+    /// Verify a variadic positional takes the values left over by the
+    /// positionals before and after it
+    #[test]
+    fn test_variadic_positionals() {
+        let parser = new("test parser".to_string())
+            .add_flag(
+                "verbose".to_string(),
+                Some("verbose".to_string()),
+                None,
+                false,
+                None,
+                "Flag verbose".to_string(),
+            )
+            .add_arg(
+                ArgConfig::new("src".to_string())
+                    .arg_type(ArgType::Integer)
+                    .action(Action::Append)
+                    .min_occurrences(1)
+                    .max_occurrences(3),
+            )
+            .add_arg(
+                ArgConfig::new("dst".to_string())
+                    .arg_type(ArgType::String)
+                    .required(true),
+            );
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        let p = parse(&["command", "1", "2", "--verbose", "3", "dst_val"]).unwrap();
+        assert_eq!(
+            p.get_arg("src"),
+            Some(&Arg::List(vec![
+                Arg::Integer(1),
+                Arg::Integer(2),
+                Arg::Integer(3)
+            ]))
+        );
+        assert_eq!(p.get_arg("dst"), Some(&Arg::String("dst_val".to_string())));
+        assert_eq!(p.occurrences("src"), 3);
+
+        let e = parse(&["command", "dst_val"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TooFewOccurrences);
+        assert_eq!(e.token(), Some("<src>"));
+
+        let e = parse(&["command", "1", "2", "3", "4", "dst_val"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TooManyPositionals);
+        assert_eq!(e.token(), Some("4"));
+        assert_eq!(e.index(), Some(4));
+
+        let e = parse(&["command", "1", "x", "dst_val"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+        assert_eq!(e.index(), Some(2));
+
+        // Optional trailing variadic
+        let parser = new("test parser".to_string())
+            .add_flag(
+                "first".to_string(),
+                None,
+                None,
+                true,
+                Some(ArgType::String),
+                "First".to_string(),
+            )
+            .add_arg(
+                ArgConfig::new("rest".to_string())
+                    .arg_type(ArgType::String)
+                    .action(Action::Append),
+            );
+        let args = vec!["command".to_string(), "a".to_string()];
+        let p = parser.try_parse(args.into_iter()).unwrap();
+        assert_eq!(p.get_arg("first"), Some(&Arg::String("a".to_string())));
+        assert_eq!(p.get_arg("rest"), Some(&Arg::List(vec![])));
+    }
+
+    /// Verify values are taken from argv, then the environment, then defaults
+    #[test]
+    fn test_defaults_and_env() {
+        env::set_var("SIMPLEARGS_TEST_DST", "10.0.0.1");
+        env::set_var("SIMPLEARGS_TEST_COUNT", "7");
+        env::set_var("SIMPLEARGS_TEST_QUIET", "yes");
+        env::set_var("SIMPLEARGS_TEST_BAD", "abc");
+        env::set_var("SIMPLEARGS_TEST_TAGS", "a,b");
+        env::set_var("SIMPLEARGS_TEST_RANGE", "500");
+
+        let parser = new("test parser".to_string())
+            .add_arg(
+                ArgConfig::new("dst".to_string())
+                    .arg_type(ArgType::String)
+                    .env("SIMPLEARGS_TEST_DST".to_string())
+                    .default_value(Arg::String("127.0.0.1".to_string())),
+            )
+            .add_arg(
+                ArgConfig::new("count".to_string())
+                    .short_flag('c')
+                    .arg_type(ArgType::Integer)
+                    .env("SIMPLEARGS_TEST_COUNT".to_string())
+                    .default_value(Arg::Integer(1)),
+            )
+            .add_arg(
+                ArgConfig::new("interval".to_string())
+                    .short_flag('i')
+                    .arg_type(ArgType::Float)
+                    .env("SIMPLEARGS_TEST_UNSET".to_string())
+                    .default_value(Arg::Float(1.0))
+                    .description("Seconds between pings".to_string()),
+            )
+            .add_arg(
+                ArgConfig::new("quiet".to_string())
+                    .short_flag('q')
+                    .env("SIMPLEARGS_TEST_QUIET".to_string()),
+            )
+            .help_width(120);
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        let p = parse(&["command", "-c", "3", "192.168.0.1"]).unwrap();
+        assert_eq!(p.get_arg("count"), Some(&Arg::Integer(3)));
+        assert_eq!(p.source("count"), Some(ValueSource::CommandLine));
+        assert_eq!(
+            p.get_arg("dst"),
+            Some(&Arg::String("192.168.0.1".to_string()))
+        );
+        assert_eq!(p.source("dst"), Some(ValueSource::CommandLine));
+
+        let p = parse(&["command"]).unwrap();
+        assert_eq!(p.get_arg("count"), Some(&Arg::Integer(7)));
+        assert_eq!(p.source("count"), Some(ValueSource::Environment));
+        assert_eq!(p.get_arg("dst"), Some(&Arg::String("10.0.0.1".to_string())));
+        assert_eq!(p.get_arg("interval"), Some(&Arg::Float(1.0)));
+        assert_eq!(p.source("interval"), Some(ValueSource::Default));
+        assert_eq!(p.get_arg("quiet"), Some(&Arg::Boolean(true)));
+        assert_eq!(p.source("quiet"), Some(ValueSource::Environment));
+
+        let help = parser.render_help("command", HelpStyle::Short);
+        assert!(help.contains("Seconds between pings [default: 1] [env: SIMPLEARGS_TEST_UNSET]"));
+
+        let parser = new("test parser".to_string()).add_arg(
+            ArgConfig::new("bad".to_string())
+                .long_flag("bad".to_string())
+                .arg_type(ArgType::Integer)
+                .env("SIMPLEARGS_TEST_BAD".to_string()),
+        );
+        let args = vec!["command".to_string()];
+        let e = parser.try_parse(args.into_iter()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+        assert_eq!(e.arg(), Some("$SIMPLEARGS_TEST_BAD"));
+
+        let parser = new("test parser".to_string()).add_arg(
+            ArgConfig::new("percent".to_string())
+                .long_flag("percent".to_string())
+                .arg_type(ArgType::Integer)
+                .range(0..=100)
+                .env("SIMPLEARGS_TEST_RANGE".to_string()),
+        );
+        let args = vec!["command".to_string()];
+        let e = parser.try_parse(args.into_iter()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::OutOfRange);
+        assert_eq!(e.arg(), Some("$SIMPLEARGS_TEST_RANGE"));
+        assert!(e.detail().is_some_and(|detail| detail.contains("100")));
+
+        // min_occurrences only applies to the values passed in argv
+        let parser = new("test parser".to_string())
+            .add_arg(
+                ArgConfig::new("tags".to_string())
+                    .long_flag("tags".to_string())
+                    .arg_type(ArgType::String)
+                    .action(Action::Append)
+                    .delimiter(',')
+                    .min_occurrences(2)
+                    .env("SIMPLEARGS_TEST_TAGS".to_string()),
+            )
+            .add_arg(
+                ArgConfig::new("ports".to_string())
+                    .long_flag("ports".to_string())
+                    .arg_type(ArgType::Integer)
+                    .action(Action::Append)
+                    .min_occurrences(2)
+                    .default_value(Arg::List(vec![Arg::Integer(80)])),
+            );
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+        let p = parse(&["command"]).unwrap();
+        assert_eq!(p.get_many::<String>("tags").unwrap(), vec!["a", "b"]);
+        assert_eq!(p.source("tags"), Some(ValueSource::Environment));
+        assert_eq!(p.get_many::<i32>("ports").unwrap(), vec![80]);
+        let e = parse(&["command", "--ports", "443"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TooFewOccurrences);
+        assert_eq!(e.token(), Some("--ports"));
+    }
+
+    /// Verify the typed getters convert values and reject mismatched types
+    #[test]
+    fn test_typed_getters() {
+        let parser = new("test parser".to_string())
+            .add_flag(
+                "integer".to_string(),
+                Some("integer".to_string()),
+                None,
+                false,
+                Some(ArgType::Integer),
+                "Test an Integer".to_string(),
+            )
+            .add_flag(
+                "float".to_string(),
+                Some("float".to_string()),
+                None,
+                false,
+                Some(ArgType::Float),
+                "Test an Float".to_string(),
+            )
+            .add_flag(
+                "boolean".to_string(),
+                Some("boolean".to_string()),
+                None,
+                false,
+                None,
+                "Test an boolean".to_string(),
+            )
+            .add_arg(
+                ArgConfig::new("strings".to_string())
+                    .arg_type(ArgType::String)
+                    .action(Action::Append),
+            );
+
+        let args = vec!["command", "--integer=300", "a", "b"];
+        let p = parser
+            .try_parse(args.into_iter().map(|arg| arg.to_string()))
+            .unwrap();
+
+        assert_eq!(p.get::<i32>("integer").unwrap(), Some(300));
+        assert_eq!(p.get::<i64>("integer").unwrap(), Some(300));
+        assert_eq!(p.get::<f32>("float").unwrap(), None);
+        assert_eq!(p.get_or::<f32>("float", 1.5).unwrap(), 1.5);
+        assert_eq!(p.get::<bool>("boolean").unwrap(), Some(false));
+        assert_eq!(
+            p.get_many::<String>("strings").unwrap(),
+            vec!["a".to_string(), "b".to_string()]
+        );
+        assert_eq!(p.get_many::<i32>("integer").unwrap(), vec![300]);
+        assert_eq!(p.get::<i32>("undefined").unwrap(), None);
+
+        let e = p.get::<String>("integer").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TypeMismatch);
+        assert_eq!(e.arg(), Some("integer"));
+        assert_eq!(
+            e.to_string(),
+            "can't read 'integer' as the requested type: its type is Integer"
+        );
+
+        // The declared type is checked even if the arg has no value
+        let e = p.get::<u32>("float").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TypeMismatch);
+        assert_eq!(e.detail(), Some("its type is Float"));
+        assert!(p.get_or::<String>("boolean", String::new()).is_err());
+        assert!(p.get_many::<bool>("strings").is_err());
+
+        // Values that don't fit are mismatches too
+        let e = p.get::<u8>("integer").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TypeMismatch);
+        assert_eq!(e.detail(), Some("its value is 300"));
+    }
+
+    /// Verify applications can plug in their own value parsers
+    #[test]
+    fn test_custom_value_parser() {
+        /// Accepts only even integers
+        struct EvenParser;
+
+        impl ValueParser for EvenParser {
+            fn parse(&self, value: &str) -> Result<Arg, String> {
+                match value.parse::<i32>() {
+                    Ok(val) if val % 2 == 0 => Ok(Arg::Integer(val)),
+                    _ => Err("expected an even number".to_string()),
+                }
+            }
+
+            fn type_name(&self) -> String {
+                "Even".to_string()
+            }
+        }
+
+        let parser = new("test parser".to_string())
+            .add_arg(
+                ArgConfig::new("even".to_string())
+                    .long_flag("even".to_string())
+                    .arg_type(ArgType::custom(EvenParser)),
+            )
+            .add_arg(
+                ArgConfig::new("ip".to_string())
+                    .arg_type(ArgType::custom(FromStrParser::<IpAddr>::new("IP address"))),
+            );
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        let p = parse(&["command", "--even=4", "::1"]).unwrap();
+        assert_eq!(p.get::<i32>("even").unwrap(), Some(4));
+        assert_eq!(
+            p.get::<IpAddr>("ip").unwrap(),
+            Some(IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]))
+        );
+        assert_eq!(p.get_arg("ip").unwrap().to_string(), "::1");
+
+        let e = parse(&["command", "--even=3"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+        assert_eq!(
+            e.to_string(),
+            "invalid value '3' for '--even': expected an even number (argument 1)"
+        );
+
+        let e = parse(&["command", "localhost"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+        assert_eq!(e.arg(), Some("<ip>"));
+        assert_eq!(
+            e.detail(),
+            Some("expected IP address (invalid IP address syntax)")
+        );
+    }
+
+    /// Verify bash, zsh and fish completions cover flags, values and subcommands
+    #[test]
+    fn test_completions() {
+        let parser = new("test parser".to_string())
+            .add_global_flag(
+                "verbose".to_string(),
+                Some("verbose".to_string()),
+                Some('v'),
+                false,
+                None,
+                "Print more output".to_string(),
+            )
+            .add_arg(
+                ArgConfig::new("config".to_string())
+                    .long_flag("config".to_string())
+                    .arg_type(ArgType::String)
+                    .value_hint(ValueHint::FilePath)
+                    .description("Read settings from [file]".to_string()),
+            )
+            .add_subcommand(
+                "remote".to_string(),
+                new("Manage remotes".to_string()).add_subcommand(
+                    "add".to_string(),
+                    new("Add a remote".to_string()).add_arg(
+                        ArgConfig::new("url".to_string())
+                            .arg_type(ArgType::String)
+                            .required(true),
+                    ),
+                ),
+            )
+            .completions_flag(true);
+
+        let bash = parser.completions(Shell::Bash, "tool");
+        assert!(bash.contains("'tool__remote,add')\n                cmd=tool__remote__add"));
+        assert!(bash.contains("--config)\n                    COMPREPLY=($(compgen -f"));
+        assert!(bash.contains("opts='--help --verbose -v --config remote'"));
+        assert!(bash.ends_with("complete -F _tool tool\n"));
+
+        let zsh = parser.completions(Shell::Zsh, "tool");
+        assert!(zsh.starts_with("#compdef tool\n"));
+        assert!(zsh.contains("'(-v --verbose)'{-v,--verbose}'[Print more output]'"));
+        assert!(
+            zsh.contains("'(--config)'--config='[Read settings from \\[file\\]]:config:_files'")
+        );
+        assert!(zsh.contains("':subcommand:((remote\\:\"Manage remotes\"))'"));
+        assert!(zsh.contains("_tool__remote__add() {"));
+
+        let fish = parser.completions(Shell::Fish, "tool");
+        assert!(fish.contains(
+            "complete -c tool -n '__fish_use_subcommand' -f -a 'remote' -d 'Manage remotes'\n"
+        ));
+        assert!(fish.contains(
+            "complete -c tool -n '__fish_seen_subcommand_from add' -s v -l verbose -d 'Print more output'\n"
+        ));
+
+        // The hidden flag returns the script instead of parsing
+        let args = ["/usr/bin/tool", "--generate-completions", "fish"];
+        let e = parser
+            .try_parse(args.iter().map(|arg| arg.to_string()))
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::CompletionsRequested);
+        assert_eq!(e.help(), Some(fish.as_str()));
+
+        let args = ["tool", "--generate-completions=powershell"];
+        let e = parser
+            .try_parse(args.iter().map(|arg| arg.to_string()))
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+        assert_eq!(e.detail(), Some("expected one of bash, zsh or fish"));
+
+        // It isn't accepted unless enabled and isn't shown in the help screen
+        assert!(!parser
+            .render_help("tool", HelpStyle::Long)
+            .contains("--generate-completions"));
+        let e = new("test parser".to_string())
+            .try_parse(args.iter().map(|arg| arg.to_string()))
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnknownFlag);
+    }
+
+    /// Verify man pages and Markdown document every arg and subcommand
+    #[test]
+    fn test_man_page_and_markdown() {
+        let parser = new("Manage the tool".to_string())
+            .add_arg(
+                ArgConfig::new("count".to_string())
+                    .long_flag("count".to_string())
+                    .short_flag('c')
+                    .arg_type(ArgType::Integer)
+                    .default_value(Arg::Integer(3))
+                    .env("TOOL_COUNT".to_string())
+                    .description("Stop after count replies".to_string()),
+            )
+            .add_subcommand(
+                "remote".to_string(),
+                new("Manage remotes".to_string())
+                    .add_arg(
+                        ArgConfig::new("url".to_string())
+                            .arg_type(ArgType::String)
+                            .required(true)
+                            .description("The remote's URL".to_string()),
+                    )
+                    .example(
+                        "tool remote https://example.com".to_string(),
+                        "Add example.com".to_string(),
+                    ),
+            )
+            .example("tool -c 5".to_string(), "Run 5 times".to_string());
+
+        let man_page = parser.man_page("tool", 1);
+        assert!(man_page.starts_with(".TH TOOL 1\n.SH NAME\ntool \\- Manage the tool\n"));
+        assert!(man_page.contains(".SH SYNOPSIS\n\\fBtool\\fR [options] <subcommand>\n"));
+        assert!(man_page.contains(
+            ".TP\n\\fB\\-c\\fR, \\fB\\-\\-count\\fR \\fIcount\\fR\nStop after count replies\n\
+             .IP\n[default: 3, env: TOOL_COUNT]\n"
+        ));
+        assert!(man_page.contains(".SH \"tool remote\"\nManage remotes\n"));
+        assert!(man_page.contains(".SS ARGUMENTS\n.TP\n\\fI<url>\\fR\nThe remote's URL\n"));
+        assert!(man_page.contains(".SH EXAMPLES\n.TP\n\\fBtool \\-c 5\\fR\nRun 5 times\n"));
+
+        let markdown = parser.markdown("tool");
+        assert!(markdown
+            .starts_with("# tool\n\nManage the tool\n\n```\ntool [options] <subcommand>\n```\n"));
+        assert!(markdown.contains(
+            "- `-c, --count <count>`: Stop after count replies (default: 3, env: TOOL_COUNT)\n"
+        ));
+        assert!(markdown.contains("\n## tool remote\n"));
+        assert!(markdown.contains("- `<url>`: The remote's URL (required)\n"));
+        assert!(markdown.contains(
+            "\n### Examples\n\nAdd example.com\n\n```sh\ntool remote https://example.com\n```\n"
+        ));
+
+        assert!(parser
+            .render_help("tool", HelpStyle::Long)
+            .contains("\nExamples:\n  tool -c 5\n          Run 5 times\n"));
+    }
+
+    /// Verify clustered short flags and values attached to short flags
+    #[test]
+    fn test_short_flag_clusters() {
+        let parser = new("test parser".to_string())
+            .add_flag(
+                "extract".to_string(),
+                None,
+                Some('x'),
+                false,
+                None,
+                "Extract".to_string(),
+            )
+            .add_flag(
+                "verbose".to_string(),
+                None,
+                Some('v'),
+                false,
+                None,
+                "Verbose".to_string(),
+            )
+            .add_flag(
+                "file".to_string(),
+                None,
+                Some('f'),
+                false,
+                Some(ArgType::String),
+                "Archive file".to_string(),
+            )
+            .add_arg(
+                ArgConfig::new("members".to_string())
+                    .arg_type(ArgType::String)
+                    .action(Action::Append),
+            );
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        let p = parse(&["command", "-xvf", "archive.tar"]).unwrap();
+        assert_eq!(p.get::<bool>("extract").unwrap(), Some(true));
+        assert_eq!(p.get::<bool>("verbose").unwrap(), Some(true));
+        assert_eq!(
+            p.get::<String>("file").unwrap(),
+            Some("archive.tar".to_string())
+        );
+
+        // The rest of the cluster after a flag taking a value is its value
+        let p = parse(&["command", "-vfarchive.tar"]).unwrap();
+        assert_eq!(p.get::<bool>("extract").unwrap(), Some(false));
+        assert_eq!(
+            p.get::<String>("file").unwrap(),
+            Some("archive.tar".to_string())
+        );
+        let p = parse(&["command", "-xf=archive.tar"]).unwrap();
+        assert_eq!(
+            p.get::<String>("file").unwrap(),
+            Some("archive.tar".to_string())
+        );
+
+        let e = parse(&["command", "-xq"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnknownFlag);
+        assert_eq!(e.token(), Some("-q"));
+
+        let e = parse(&["command", "-xf"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::MissingValue);
+        assert_eq!(e.token(), Some("-f"));
+
+        // Everything after "--" is positional
+        let p = parse(&["command", "-v", "--", "-x", "--file=a", "--"]).unwrap();
+        assert_eq!(p.get::<bool>("extract").unwrap(), Some(false));
+        assert_eq!(p.get::<String>("file").unwrap(), None);
+        assert_eq!(
+            p.get_many::<String>("members").unwrap(),
+            vec!["-x".to_string(), "--file=a".to_string(), "--".to_string()]
+        );
+    }
+
+    /// Verify negative numbers and values starting with a hyphen are taken as values
+    #[test]
+    fn test_negative_numbers_and_hyphen_values() {
+        let parser = new("test parser".to_string())
+            .add_arg(
+                ArgConfig::new("offset".to_string())
+                    .long_flag("offset".to_string())
+                    .arg_type(ArgType::Integer64),
+            )
+            .add_arg(
+                ArgConfig::new("temp".to_string())
+                    .long_flag("temp".to_string())
+                    .short_flag('t')
+                    .arg_type(ArgType::Double),
+            )
+            .add_arg(
+                ArgConfig::new("name".to_string())
+                    .long_flag("name".to_string())
+                    .arg_type(ArgType::String),
+            )
+            .add_arg(
+                ArgConfig::new("pattern".to_string())
+                    .long_flag("pattern".to_string())
+                    .arg_type(ArgType::String)
+                    .allow_hyphen_values(true),
+            )
+            .add_arg(
+                ArgConfig::new("five".to_string())
+                    .short_flag('5')
+                    .description("A flag named like a number".to_string()),
+            )
+            .add_arg(ArgConfig::new("delta".to_string()).arg_type(ArgType::Integer));
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        let p = parse(&["command", "--offset", "-7", "--temp=-3.2", "-1"]).unwrap();
+        assert_eq!(p.get::<i64>("offset").unwrap(), Some(-7));
+        assert_eq!(p.get::<f64>("temp").unwrap(), Some(-3.2));
+        assert_eq!(p.get::<i32>("delta").unwrap(), Some(-1));
+
+        let p = parse(&["command", "-t", "-.5", "--offset", "-0x10"]).unwrap();
+        assert_eq!(p.get::<f64>("temp").unwrap(), Some(-0.5));
+        assert_eq!(p.get::<i64>("offset").unwrap(), Some(-16));
+
+        // A defined short flag wins over a negative number
+        let e = parse(&["command", "--offset", "-5"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::ArgValueIsFlag);
+        let p = parse(&["command", "-5", "-2"]).unwrap();
+        assert_eq!(p.get::<bool>("five").unwrap(), Some(true));
+        assert_eq!(p.get::<i32>("delta").unwrap(), Some(-2));
+
+        // Strings only take hyphen values if attached or allowed
+        let e = parse(&["command", "--name", "-x"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::ArgValueIsFlag);
+        assert_eq!(e.arg(), Some("--name"));
+        let p = parse(&["command", "--name=-x", "--pattern", "-y"]).unwrap();
+        assert_eq!(p.get::<String>("name").unwrap(), Some("-x".to_string()));
+        assert_eq!(p.get::<String>("pattern").unwrap(), Some("-y".to_string()));
+    }
+
+    /// Verify unknown flags and subcommands suggest the closest known name
+    #[test]
+    fn test_suggestions() {
+        assert_eq!(suggest::edit_distance("verbose", "verbose"), 0);
+        assert_eq!(suggest::edit_distance("verbos", "verbose"), 1);
+        assert_eq!(suggest::edit_distance("kitten", "sitting"), 3);
+        assert_eq!(suggest::edit_distance("hlep", "help"), 1);
+        assert_eq!(suggest::edit_distance("", "abc"), 3);
+
+        let parser = new("test parser".to_string())
+            .add_global_flag(
+                "verbose".to_string(),
+                Some("verbose".to_string()),
+                Some('v'),
+                false,
+                None,
+                "Verbose".to_string(),
+            )
+            .add_flag(
+                "version".to_string(),
+                Some("version".to_string()),
+                None,
+                false,
+                None,
+                "Version".to_string(),
+            )
+            .add_subcommand("remote".to_string(), new("Manage remotes".to_string()))
+            .add_subcommand("status".to_string(), new("Show status".to_string()));
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        let e = parse(&["command", "--verbos"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnknownFlag);
+        assert_eq!(
+            e.to_string(),
+            "flag '--verbos' is not defined: did you mean '--verbose'? (argument 1)"
+        );
+
+        let e = parse(&["command", "--versoin"]).unwrap_err();
+        assert_eq!(e.detail(), Some("did you mean '--version'?"));
+        let e = parse(&["command", "-V"]).unwrap_err();
+        assert_eq!(e.detail(), Some("did you mean '-v'?"));
+        let e = parse(&["command", "--hlep"]).unwrap_err();
+        assert_eq!(e.detail(), Some("did you mean '--help'?"));
+
+        // Global flags are suggested in subcommands, unrelated flags aren't
+        let e = parse(&["command", "remote", "--verbsoe"]).unwrap_err();
+        assert_eq!(e.detail(), Some("did you mean '--verbose'?"));
+        let e = parse(&["command", "--quiet"]).unwrap_err();
+        assert_eq!(e.detail(), None);
+        let e = parse(&["command", "-vx"]).unwrap_err();
+        assert_eq!(e.token(), Some("-x"));
+        assert_eq!(e.detail(), None);
+
+        let e = parse(&["command", "remtoe"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnknownSubcommand);
+        assert_eq!(
+            e.to_string(),
+            "unknown subcommand 'remtoe': did you mean 'remote'? (argument 1)"
+        );
+        let e = parse(&["command", "commit"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnknownSubcommand);
+        assert_eq!(e.detail(), None);
+    }
+
+    /// Verify groups, conflicts and requirements between args are enforced
+    #[test]
+    fn test_groups_and_relationships() {
+        let flag = |name: &str| {
+            ArgConfig::new(name.to_string())
+                .long_flag(name.to_string())
+                .arg_type(ArgType::String)
+        };
+        let parser = new("test parser".to_string())
+            .add_arg(flag("file"))
+            .add_arg(flag("url"))
+            .add_arg(ArgConfig::new("json".to_string()).long_flag("json".to_string()))
+            .add_arg(
+                ArgConfig::new("quiet".to_string())
+                    .short_flag('q')
+                    .conflicts_with("json".to_string()),
+            )
+            .add_arg(flag("user").requires("password".to_string()))
+            .add_arg(flag("password").env("SIMPLEARGS_TEST_GROUPS_PASSWORD".to_string()))
+            .add_arg(flag("token").requires("auth".to_string()))
+            .add_group(
+                ArgGroup::new("source".to_string())
+                    .arg("file".to_string())
+                    .arg("url".to_string())
+                    .required(true),
+            )
+            .add_group(
+                ArgGroup::new("auth".to_string())
+                    .arg("user".to_string())
+                    .arg("password".to_string())
+                    .multiple(true),
+            );
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        assert!(parse(&["command", "--file", "a"]).is_ok());
+        assert!(parse(&["command", "--url", "b", "--json"]).is_ok());
+
+        let e = parse(&["command", "--file", "a", "--url", "b"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::ArgumentConflict);
+        assert_eq!(e.to_string(), "'--url' cannot be used with '--file'");
+        assert!(e.help().unwrap().contains("Usage:"));
+
+        let e = parse(&["command"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::MissingGroup);
+        assert_eq!(e.to_string(), "one of '--file | --url' is required");
+        assert_eq!(e.arg(), Some("source"));
+
+        let e = parse(&["command", "--file", "a", "-q", "--json"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::ArgumentConflict);
+        assert_eq!(e.to_string(), "'-q' cannot be used with '--json'");
+
+        let e = parse(&["command", "--file", "a", "--user", "me"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::MissingRequired);
+        assert_eq!(
+            e.to_string(),
+            "missing required argument '--password': required by '--user'"
+        );
+        assert!(parse(&["command", "--file", "a", "--user", "me", "--password", "pw"]).is_ok());
+
+        // Requiring a group is satisfied by any of its members
+        let e = parse(&["command", "--file", "a", "--token", "t"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::MissingGroup);
+        assert_eq!(
+            e.to_string(),
+            "one of '--user | --password' is required: required by '--token'"
+        );
+
+        // Values from the environment count as passed, defaults don't
+        env::set_var("SIMPLEARGS_TEST_GROUPS_PASSWORD", "pw");
+        assert!(parse(&["command", "--file", "a", "--token", "t"]).is_ok());
+        env::remove_var("SIMPLEARGS_TEST_GROUPS_PASSWORD");
+
+        assert!(parser
+            .render_help("command", HelpStyle::Short)
+            .contains("Usage: command [options] (--file <file> | --url <url>)\n"));
+
+        // Naming an argument that doesn't exist is a bug in the parser
+        let parser = new("test parser".to_string())
+            .add_arg(ArgConfig::new("json".to_string()).long_flag("json".to_string()))
+            .add_arg(
+                ArgConfig::new("quiet".to_string())
+                    .long_flag("quiet".to_string())
+                    .conflicts_with("jsno".to_string()),
+            )
+            .add_group(
+                ArgGroup::new("output".to_string())
+                    .arg("json".to_string())
+                    .arg("quiet".to_string()),
+            );
+        assert!(parser
+            .render_help("command", HelpStyle::Short)
+            .contains("Usage: command [options] [--json | --quiet]\n"));
+        let e = parser
+            .try_parse(["command", "--quiet"].iter().map(|arg| arg.to_string()))
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidConfig);
+        assert_eq!(e.token(), Some("jsno"));
+    }
+
+    /// Verify counted flags add up their occurrences and --no- flags negate
+    #[test]
+    fn test_counted_and_negatable_flags() {
+        let parser = new("test parser".to_string())
+            .help_width(80)
+            .add_arg(
+                ArgConfig::new("verbose".to_string())
+                    .short_flag('v')
+                    .long_flag("verbose".to_string())
+                    .action(Action::Count)
+                    .max_occurrences(3)
+                    .env("SIMPLEARGS_TEST_VERBOSE".to_string())
+                    .description("More output".to_string()),
+            )
+            .add_arg(
+                ArgConfig::new("color".to_string())
+                    .long_flag("color".to_string())
+                    .negatable(true)
+                    .default_value(Arg::Boolean(true))
+                    .description("Colorize the output".to_string()),
+            )
+            .add_arg(
+                ArgConfig::new("quiet".to_string())
+                    .short_flag('q')
+                    .description("Less output".to_string()),
+            );
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        let matches = parse(&["command"]).unwrap();
+        assert_eq!(matches.get::<u8>("verbose").unwrap(), Some(0));
+        assert_eq!(matches.get::<bool>("color").unwrap(), Some(true));
+        assert_eq!(matches.source("color"), Some(ValueSource::Default));
+
+        let matches = parse(&["command", "-vqv", "--verbose"]).unwrap();
+        assert_eq!(matches.get::<u8>("verbose").unwrap(), Some(3));
+        assert_eq!(matches.get_arg("verbose"), Some(&Arg::Unsigned64(3)));
+        let e = parse(&["command", "-vvvv"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TooManyOccurrences);
+
+        env::set_var("SIMPLEARGS_TEST_VERBOSE", "2");
+        let matches = parse(&["command"]).unwrap();
+        assert_eq!(matches.get::<u8>("verbose").unwrap(), Some(2));
+        assert_eq!(matches.source("verbose"), Some(ValueSource::Environment));
+        env::remove_var("SIMPLEARGS_TEST_VERBOSE");
+
+        // The last occurrence wins
+        let matches = parse(&["command", "--no-color"]).unwrap();
+        assert_eq!(matches.get::<bool>("color").unwrap(), Some(false));
+        let matches = parse(&["command", "--no-color", "--color"]).unwrap();
+        assert_eq!(matches.get::<bool>("color").unwrap(), Some(true));
+        let matches = parse(&["command", "--color", "--no-color"]).unwrap();
+        assert_eq!(matches.get::<bool>("color").unwrap(), Some(false));
+        assert_eq!(matches.occurrences("color"), 2);
+
+        // Only negatable flags have a "--no-" form
+        let e = parse(&["command", "--no-verbose"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnknownFlag);
+        let e = parse(&["command", "--no-colour"]).unwrap_err();
+        assert_eq!(e.detail(), Some("did you mean '--no-color'?"));
+
+        let help = parser.render_help("command", HelpStyle::Short);
+        assert!(help.contains("      --[no-]color  Colorize the output [default: true]\n"));
+        assert!(parser
+            .man_page("command", 1)
+            .contains("\\fB\\-\\-[no\\-]color\\fR"));
+        assert!(parser
+            .completions(Shell::Bash, "command")
+            .contains("--color --no-color"));
+        assert!(parser
+            .completions(Shell::Zsh, "command")
+            .contains("'*'{-v,--verbose}"));
+        assert!(parser
+            .completions(Shell::Fish, "command")
+            .contains("complete -c command -l no-color -d 'Colorize the output'\n"));
+    }
+
+    /// Verify choice args only take their listed values and show them in help
+    #[test]
+    fn test_choices() {
+        #[derive(Clone, Debug, PartialEq)]
+        enum Level {
+            Low,
+            High,
+        }
+
+        impl ValueEnum for Level {
+            fn variants() -> Vec<(&'static str, Self)> {
+                vec![("low", Level::Low), ("high", Level::High)]
+            }
+        }
+
+        let parser = new("test parser".to_string())
+            .help_width(80)
+            .add_arg(
+                ArgConfig::new("format".to_string())
+                    .long_flag("format".to_string())
+                    .arg_type(ArgType::choices(&["text", "json", "csv"]))
+                    .default_value(Arg::from("text"))
+                    .description("Output format".to_string()),
+            )
+            .add_arg(
+                ArgConfig::new("level".to_string())
+                    .long_flag("level".to_string())
+                    .arg_type(ArgType::custom(
+                        ChoiceParser::of::<Level>().ignore_case(true),
+                    )),
+            );
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        let matches = parse(&["command", "--format", "csv", "--level=HIGH"]).unwrap();
+        assert_eq!(
+            matches.get::<String>("format").unwrap(),
+            Some("csv".to_string())
+        );
+        assert_eq!(matches.get::<Level>("level").unwrap(), Some(Level::High));
+        let matches = parse(&["command", "--level", "low"]).unwrap();
+        assert_eq!(
+            matches.get::<String>("format").unwrap(),
+            Some("text".to_string())
+        );
+        assert_eq!(matches.get::<Level>("level").unwrap(), Some(Level::Low));
+        assert!(matches.get::<Level>("format").is_err());
+
+        let e = parse(&["command", "--format", "JSON"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+        assert_eq!(
+            e.to_string(),
+            "invalid value 'JSON' for '--format': expected one of text, json or csv, \
+             did you mean 'json'? (argument 2)"
+        );
+        let e = parse(&["command", "--level", "medium"]).unwrap_err();
+        assert_eq!(e.detail(), Some("expected one of low or high"));
+
+        let help = parser.render_help("command", HelpStyle::Short);
+        assert!(help.contains(
+            "      --format <format>  Output format [possible values: text, json, csv]\n"
+        ));
+        assert!(parser
+            .render_help("command", HelpStyle::Long)
+            .contains("[type: Choice]"));
+        assert!(parser
+            .markdown("command")
+            .contains("(possible values: text, json, csv, default: text)"));
+        assert!(parser
+            .completions(Shell::Fish, "command")
+            .contains("-l format -x -a 'text json csv'"));
+        assert!(parser
+            .completions(Shell::Bash, "command")
+            .contains("compgen -W 'low high'"));
+    }
+
+    /// Verify config file values rank below argv and the environment
+    #[test]
+    fn test_config_file() {
+        let dir = env::temp_dir().join(format!("simpleargs_test_config_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, contents: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            path.display().to_string()
+        };
+
+        let parser = new("test parser".to_string())
+            .add_arg(
+                ArgConfig::new("config".to_string())
+                    .long_flag("config".to_string())
+                    .short_flag('C')
+                    .arg_type(ArgType::String),
+            )
+            .add_arg(
+                ArgConfig::new("count".to_string())
+                    .long_flag("count".to_string())
+                    .arg_type(ArgType::Unsigned16)
+                    .range(1..=100)
+                    .env("SIMPLEARGS_TEST_CONFIG_COUNT".to_string())
+                    .default_value(Arg::Unsigned16(1)),
+            )
+            .add_arg(
+                ArgConfig::new("tag".to_string())
+                    .long_flag("tag".to_string())
+                    .arg_type(ArgType::String)
+                    .action(Action::Append),
+            )
+            .add_arg(ArgConfig::new("dry_run".to_string()).long_flag("dry-run".to_string()))
+            .add_arg(ArgConfig::new("apply".to_string()).long_flag("apply".to_string()))
+            .add_group(
+                ArgGroup::new("mode".to_string())
+                    .arg("dry_run".to_string())
+                    .arg("apply".to_string()),
+            )
+            .add_arg(
+                ArgConfig::new("verbose".to_string())
+                    .short_flag('v')
+                    .action(Action::Count),
+            )
+            .add_arg(
+                ArgConfig::new("name".to_string())
+                    .long_flag("name".to_string())
+                    .arg_type(ArgType::String)
+                    .required(true),
+            )
+            .add_subcommand(
+                "remote".to_string(),
+                new("Manage remotes".to_string()).add_arg(
+                    ArgConfig::new("url".to_string())
+                        .long_flag("url".to_string())
+                        .arg_type(ArgType::String),
+                ),
+            )
+            .config_file_flag("config".to_string());
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        let toml = write(
+            "config.toml",
+            "# Settings\n\
+             count = 5 # packets\n\
+             tag = [\"a\", 'b']\n\
+             dry-run = true\n\
+             verbose = 2\n\
+             name = \"x \\\"y\\\"\"\n\
+             \n\
+             [remote]\n\
+             url = \"https://example.com\"\n",
+        );
+        let ini = write(
+            "config.ini",
+            "; Settings\n\
+             count = 5\n\
+             tag = a\n\
+             tag: b\n\
+             dry-run = yes\n\
+             verbose = 2\n\
+             name = \"x \"y\"\"\n\
+             [remote]\n\
+             url = https://example.com\n",
+        );
+        let json = write(
+            "config.json",
+            "{\n\
+               \"count\": 5,\n\
+               \"tag\": [\"a\", \"b\"],\n\
+               \"dry-run\": true,\n\
+               \"verbose\": 2,\n\
+               \"name\": \"x \\\"y\\\"\",\n\
+               \"remote\": {\"url\": \"https://example.com\"}\n\
+             }\n",
+        );
+        for path in [&toml, &ini, &json] {
+            let matches = parse(&["command", "--config", path, "remote"]).unwrap();
+            assert_eq!(matches.get::<u16>("count").unwrap(), Some(5));
+            assert_eq!(matches.source("count"), Some(ValueSource::ConfigFile));
+            assert_eq!(
+                matches.get_many::<String>("tag").unwrap(),
+                vec!["a".to_string(), "b".to_string()]
+            );
+            assert_eq!(matches.get::<bool>("dry_run").unwrap(), Some(true));
+            assert_eq!(matches.get::<u8>("verbose").unwrap(), Some(2));
+            assert_eq!(
+                matches.get::<String>("name").unwrap(),
+                Some("x \"y\"".to_string())
+            );
+            let (_, remote) = matches.subcommand().unwrap();
+            assert_eq!(
+                remote.get::<String>("url").unwrap(),
+                Some("https://example.com".to_string())
+            );
+        }
+
+        // argv > env > file > default
+        let matches = parse(&["command", "-C", &toml, "--count", "7"]).unwrap();
+        assert_eq!(matches.get::<u16>("count").unwrap(), Some(7));
+        assert_eq!(matches.source("count"), Some(ValueSource::CommandLine));
+        env::set_var("SIMPLEARGS_TEST_CONFIG_COUNT", "6");
+        let matches = parse(&["command", "--config", &toml]).unwrap();
+        assert_eq!(matches.get::<u16>("count").unwrap(), Some(6));
+        env::remove_var("SIMPLEARGS_TEST_CONFIG_COUNT");
+        let matches = parse(&["command", "--name", "n"]).unwrap();
+        assert_eq!(matches.get::<u16>("count").unwrap(), Some(1));
+        assert_eq!(matches.source("count"), Some(ValueSource::Default));
+
+        // The flag is found in clusters and with attached values
+        let cluster = format!("-vC{}", toml);
+        for args in [vec!["command", "-vC", &toml], vec!["command", &cluster]] {
+            let matches = parse(&args).unwrap();
+            assert_eq!(matches.get::<u16>("count").unwrap(), Some(5));
+            assert_eq!(matches.get::<u8>("verbose").unwrap(), Some(1));
+        }
+        // A value that looks like the flag isn't the flag
+        let matches = parse(&["command", "--name=--config", "--config", &toml]).unwrap();
+        assert_eq!(matches.get::<u16>("count").unwrap(), Some(5));
+        assert_eq!(
+            matches.get::<String>("name").unwrap(),
+            Some("--config".to_string())
+        );
+
+        // Values from the file don't conflict with the command line
+        let matches = parse(&["command", "--config", &toml, "--apply"]).unwrap();
+        assert_eq!(matches.get::<bool>("apply").unwrap(), Some(true));
+        let e = parse(&["command", "--name", "n", "--dry-run", "--apply"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::ArgumentConflict);
+
+        // Errors name the file and line
+        let path = write("bad_type.toml", "name = \"x\"\n\ncount = \"many\"\n");
+        let e = parse(&["command", "--config", &path]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+        assert_eq!(
+            (e.file(), e.line(), e.index()),
+            (Some(path.as_str()), Some(3), None)
+        );
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "invalid value 'many' for '--count': expected an integer (invalid digit \
+                 found in string) ({}:3)",
+                path
+            )
+        );
+        let path = write("bad_range.json", "{\n  \"count\": 500\n}");
+        let e = parse(&["command", "--config", &path]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::OutOfRange);
+        assert_eq!(e.line(), Some(2));
+        let path = write("unknown.ini", "name = x\n[remote]\nuri = x\n");
+        let e = parse(&["command", "--config", &path]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnknownFlag);
+        assert_eq!(e.detail(), Some("did you mean 'url'?"));
+        assert_eq!(e.line(), Some(3));
+        let path = write("unknown.toml", "[remtoe]\n");
+        let e = parse(&["command", "--config", &path]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnknownSubcommand);
+        assert_eq!(e.line(), Some(1));
+        let path = write("syntax.toml", "name = \"x\"\ncount = [1, 2\n");
+        let e = parse(&["command", "--config", &path]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::ConfigFile);
+        assert_eq!(
+            e.to_string(),
+            format!("can't load the config file: expected ']' ({}:2)", path)
+        );
+        let path = write("syntax.json", "{\"name\": \"x\",\n\"count\": tru}");
+        let e = parse(&["command", "--config", &path]).unwrap_err();
+        assert_eq!(e.detail(), Some("invalid value 'tru'"));
+        assert_eq!(e.line(), Some(2));
+
+        // A missing default config file is fine, a missing given one isn't
+        let missing = dir.join("missing.toml");
+        let e = parse(&["command", "--config", &missing.display().to_string()]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::ConfigFile);
+        let parser = parser.default_config_file(missing);
+        assert!(parser
+            .try_parse(["command", "--name", "n"].iter().map(|arg| arg.to_string()))
+            .is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Verify @file args are expanded with shell-like quoting
+    #[test]
+    fn test_response_files() {
+        let dir = env::temp_dir().join(format!("simpleargs_test_response_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, contents: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            format!("@{}", path.display())
+        };
+
+        let parser = new("test parser".to_string())
+            .add_arg(
+                ArgConfig::new("count".to_string())
+                    .short_flag('c')
+                    .arg_type(ArgType::Integer),
+            )
+            .add_arg(
+                ArgConfig::new("hosts".to_string())
+                    .arg_type(ArgType::String)
+                    .action(Action::Append),
+            )
+            .response_files(true);
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+        let hosts = |matches: &Matches| matches.get_many::<String>("hosts").unwrap();
+
+        let more = write("more.txt", "host3 'host 4'\n");
+        let args = write(
+            "args.txt",
+            &format!(
+                "# Hosts to ping\n\
+                 -c=3 host1 # the first one\n\
+                 \"host\\\"2\\\"\" ''\n\
+                 {}\n\
+                 multi\\\n\
+                 line\n",
+                more
+            ),
+        );
+        let matches = parse(&["command", &args, "host5"]).unwrap();
+        assert_eq!(matches.get::<i32>("count").unwrap(), Some(3));
+        assert_eq!(
+            hosts(&matches),
+            vec![
+                "host1",
+                "host\"2\"",
+                "",
+                "host3",
+                "host 4",
+                "multiline",
+                "host5"
+            ]
+        );
+
+        // Values of the response file are reported at the index of "@path"
+        let bad = write("bad_count.txt", "-c x");
+        let e = parse(&["command", "host", &bad]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+        assert_eq!(e.index(), Some(2));
+
+        // Nothing after "--" is expanded, and "@" alone isn't a file
+        let matches = parse(&["command", "--", &more, "@"]).unwrap();
+        assert_eq!(hosts(&matches), vec![more.as_str(), "@"]);
+        let matches = parse(&["command", &write("dashes.txt", "-- @x"), &more]).unwrap();
+        assert_eq!(hosts(&matches), vec!["@x", more.as_str()]);
+
+        let e = parse(&["command", "@/nonexistent/simpleargs/args.txt"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::ResponseFile);
+        assert_eq!(e.index(), Some(1));
+
+        let quote = write("quote.txt", "host1\n'host2\nhost3\n");
+        let e = parse(&["command", &quote]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::ResponseFile);
+        assert_eq!(e.detail(), Some("unterminated single quote"));
+        assert_eq!(e.line(), Some(2));
+        // A backslash can't escape the end of the file
+        let escape = write("escape.txt", "host1 \"host2\\");
+        let e = parse(&["command", &escape]).unwrap_err();
+        assert_eq!(e.detail(), Some("unterminated double quote"));
+        assert_eq!(e.line(), Some(1));
+
+        // A cycle of response files is an error, including a file twice isn't
+        let cycle_a = dir.join("cycle_a.txt");
+        let cycle_b = write("cycle_b.txt", &format!("b @{}", cycle_a.display()));
+        let cycle_a = write("cycle_a.txt", &format!("a {}", cycle_b));
+        let e = parse(&["command", &cycle_a]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::ResponseFile);
+        assert_eq!(e.token(), Some(cycle_a.as_str()));
+        assert_eq!(e.detail(), Some("the file includes itself"));
+        let twice = write("twice.txt", &format!("{} {}", more, more));
+        assert_eq!(hosts(&parse(&["command", &twice]).unwrap()).len(), 4);
+
+        // Response files are opt-in
+        let parser = parser.response_files(false);
+        let matches = parser
+            .try_parse(["command", more.as_str()].iter().map(|arg| arg.to_string()))
+            .unwrap();
+        assert_eq!(hosts(&matches), vec![more.as_str()]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Verify the help screen wraps descriptions and shows types and defaults
+    #[test]
+    fn test_help_screen() {
+        let parser = new("Send ICMP echo requests to a host and report the replies".to_string())
+            .add_arg(
+                ArgConfig::new("count".to_string())
+                    .long_flag("count".to_string())
+                    .short_flag('c')
+                    .arg_type(ArgType::Unsigned16)
+                    .default_value(Arg::Unsigned16(3))
+                    .description("Stop after sending count packets".to_string()),
+            )
+            .add_arg(
+                ArgConfig::new("quiet".to_string())
+                    .long_flag("quiet".to_string())
+                    .description("Print only the summary".to_string()),
+            )
+            .add_arg(
+                ArgConfig::new("ttl".to_string())
+                    .short_flag('t')
+                    .arg_type(ArgType::Unsigned8)
+                    .value_name("hops".to_string())
+                    .heading("IP options".to_string())
+                    .description("Time to live".to_string()),
+            )
+            .add_arg(
+                ArgConfig::new("destination".to_string())
+                    .arg_type(ArgType::String)
+                    .required(true)
+                    .description("The host to ping".to_string()),
+            )
+            .example("ping -c 1 10.0.0.1".to_string(), "Ping once".to_string())
+            .help_width(40);
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        let e = parse(&["/usr/bin/ping", "-h"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::HelpRequested);
+        assert_eq!(
+            e.help(),
+            Some(
+                "Send ICMP echo requests to a host and\n\
+                 report the replies\n\
+                 \n\
+                 Usage: ping [options] <destination>\n\
+                 \n\
+                 Arguments:\n  \
+                 <destination>  The host to ping\n                 \
+                 [required]\n\
+                 \n\
+                 Options:\n  \
+                 -c, --count <count>  Stop after sending\n                       \
+                 count packets\n                       \
+                 [default: 3]\n      \
+                 --quiet          Print only the\n                       \
+                 summary\n  \
+                 -h, --help           Print help (see more\n                       \
+                 with '--help')\n\
+                 \n\
+                 IP options:\n  \
+                 -t <hops>  Time to live\n"
+            )
+        );
+
+        let e = parse(&["ping", "--help"]).unwrap_err();
+        let help = e.help().unwrap();
+        assert!(help.contains(
+            "  -c, --count <count>\n          Stop after sending count\n          \
+             packets [type: Unsigned16]\n          [default: 3]\n"
+        ));
+        assert!(help.contains("\nExamples:\n  ping -c 1 10.0.0.1\n          Ping once\n"));
+
+        // Errors show the short help screen
+        let e = parse(&["ping"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::MissingRequired);
+        assert!(e
+            .help()
+            .unwrap()
+            .contains("  -c, --count <count>  Stop after sending\n"));
+        assert!(!e.help().unwrap().contains("Examples:"));
+
+        // A "-h" flag of the program replaces the short help flag
+        let parser = new("test parser".to_string()).add_flag(
+            "human".to_string(),
+            None,
+            Some('h'),
+            false,
+            None,
+            "Human readable sizes".to_string(),
+        );
+        let p = parser
+            .try_parse(["du", "-h"].iter().map(|arg| arg.to_string()))
+            .unwrap();
+        assert_eq!(p.get::<bool>("human").unwrap(), Some(true));
+        assert!(parser
+            .render_help("du", HelpStyle::Short)
+            .contains("\n      --help  Print help"));
+    }
+
+    /// Verify each numeric type and range bound is checked
+    #[test]
+    fn test_numeric_types_and_ranges() {
+        let parser = new("test parser".to_string())
+            .add_arg(
+                ArgConfig::new("port".to_string())
+                    .long_flag("port".to_string())
+                    .arg_type(ArgType::Unsigned16)
+                    .range(1..=65535),
+            )
+            .add_arg(
+                ArgConfig::new("size".to_string())
+                    .long_flag("size".to_string())
+                    .arg_type(ArgType::Unsigned64),
+            )
+            .add_arg(
+                ArgConfig::new("offset".to_string())
+                    .long_flag("offset".to_string())
+                    .arg_type(ArgType::Integer64),
+            )
+            .add_arg(
+                ArgConfig::new("ratio".to_string())
+                    .long_flag("ratio".to_string())
+                    .arg_type(ArgType::Double)
+                    .range(0.0..1.0),
+            );
+
+        let parse = |args: &[&str]| parser.try_parse(args.iter().map(|arg| arg.to_string()));
+
+        let p = parse(&[
+            "command",
+            "--port=0x1F90",
+            "--size=18446744073709551615",
+            "--offset=0b101",
+            "--ratio=0.25",
+        ])
+        .unwrap();
+        assert_eq!(p.get_arg("port"), Some(&Arg::Unsigned16(8080)));
+        assert_eq!(p.get::<u64>("size").unwrap(), Some(u64::MAX));
+        assert_eq!(p.get::<i64>("offset").unwrap(), Some(5));
+        assert!(p.get::<u8>("port").is_err());
+        assert_eq!(p.get::<f64>("ratio").unwrap(), Some(0.25));
+
+        let p = parse(&["command", "--port", "0o17"]).unwrap();
+        assert_eq!(p.get::<u8>("port").unwrap(), Some(15));
+
+        let e = parse(&["command", "--port=0"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::OutOfRange);
+        assert_eq!(
+            e.to_string(),
+            "value '0' for '--port' is out of range: must be >= 1 and <= 65535 (argument 1)"
+        );
+
+        let e = parse(&["command", "--port=65536"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+        assert_eq!(e.detail(), Some("expected an integer from 0 to 65535"));
+
+        let e = parse(&["command", "--ratio=1"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::OutOfRange);
+        assert_eq!(e.detail(), Some("must be >= 0 and < 1"));
+
+        let e = parse(&["command", "--size=0xZZ"]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+    }
+
     #[test]
     fn it_works() {
         let args: Vec<String> = vec![
@@ -856,10 +3252,10 @@ mod tests {
                 true,
                 Some(ArgType::String),
                 "Test positional argument".to_string(),
-            )
-            .parse(args.into_iter());
+            );
 
         p.print_help();
+        let p = p.parse(args.into_iter());
 
         assert!(p.get_arg("verbose").is_some());
         assert!(p.get_arg("myflag").is_some());
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use super::{Arg, Error, ErrorKind, FromArg};
+
+/// Where the value of an argument came from
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ValueSource {
+    /// Passed on the command line
+    CommandLine,
+    /// Read from the argument's environment variable
+    Environment,
+    /// Read from the config file
+    ConfigFile,
+    /// The argument's default value
+    Default,
+}
+
+/// The type an argument is declared with, to check the type requested
+/// from the typed getters even when the argument has no value
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct DeclaredType {
+    /// The name help screens show, e.g. "Unsigned16"
+    pub(crate) name: String,
+    /// Any value of the type, or None for custom types, which can hold
+    /// anything
+    pub(crate) sample: Option<Arg>,
+}
+
+/// The result of successfully parsing the command line arguments
+#[derive(PartialEq, Debug)]
+pub struct Matches {
+    /// The command as it was invoked (argv[0])
+    pub(crate) command: String,
+    /// name -> arg value
+    pub(crate) parsed_args: HashMap<String, Arg>,
+    /// name -> how many times the flag was passed
+    pub(crate) occurrences: HashMap<String, usize>,
+    /// name -> where the value came from
+    pub(crate) sources: HashMap<String, ValueSource>,
+    /// name -> the type the argument is declared with
+    pub(crate) declared_types: HashMap<String, DeclaredType>,
+    /// The matches of the subcommand that was chosen, if any
+    pub(crate) subcommand: Option<Box<Matches>>,
+}
+
+impl Matches {
+    pub(crate) fn new(command: String) -> Self {
+        Matches {
+            command,
+            parsed_args: HashMap::new(),
+            occurrences: HashMap::new(),
+            sources: HashMap::new(),
+            declared_types: HashMap::new(),
+            subcommand: None,
+        }
+    }
+
+    /// The command as it was invoked (argv[0]), or the subcommand name
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Retrieve the parsed arg value
+    pub fn get_arg(&self, name: &str) -> Option<&Arg> {
+        self.parsed_args.get(name)
+    }
+
+    /// Retrieve the parsed arg value as type T. Returns None if the arg
+    /// wasn't passed and has no default, and an error if its declared type
+    /// or its value can't be converted to T.
+    ///
+    /// ```
+    /// let args = vec!["command".to_string(), "--count=3".to_string()];
+    /// let matches = simpleargs::new("example".to_string())
+    ///     .add_flag(
+    ///         "count".to_string(),
+    ///         Some("count".to_string()),
+    ///         None,
+    ///         false,
+    ///         Some(simpleargs::ArgType::Integer),
+    ///         "Number of times".to_string(),
+    ///     )
+    ///     .try_parse(args.into_iter())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(matches.get::<u16>("count").unwrap(), Some(3));
+    /// assert!(matches.get::<String>("count").is_err());
+    /// ```
+    pub fn get<T: FromArg>(&self, name: &str) -> Result<Option<T>, Error> {
+        self.check_declared_type::<T>(name)?;
+        match self.parsed_args.get(name) {
+            None | Some(Arg::None) => Ok(None),
+            Some(arg) => Self::convert(name, arg).map(Some),
+        }
+    }
+
+    /// Retrieve the parsed arg value as type T, or default if it wasn't passed
+    pub fn get_or<T: FromArg>(&self, name: &str, default: T) -> Result<T, Error> {
+        Ok(self.get(name)?.unwrap_or(default))
+    }
+
+    /// Retrieve all values of an Append flag or variadic positional as type T.
+    /// A single valued arg is returned as a list of one value.
+    pub fn get_many<T: FromArg>(&self, name: &str) -> Result<Vec<T>, Error> {
+        self.check_declared_type::<T>(name)?;
+        match self.parsed_args.get(name) {
+            None | Some(Arg::None) => Ok(vec![]),
+            Some(Arg::List(args)) => args.iter().map(|arg| Self::convert(name, arg)).collect(),
+            Some(arg) => Ok(vec![Self::convert(name, arg)?]),
+        }
+    }
+
+    /// Fail if values of the argument's declared type can't be converted
+    /// to T, whether or not the argument has a value
+    fn check_declared_type<T: FromArg>(&self, name: &str) -> Result<(), Error> {
+        match self.declared_types.get(name) {
+            Some(DeclaredType {
+                name: type_name,
+                sample: Some(sample),
+            }) if T::from_arg(sample).is_none() => Err(Error::new(ErrorKind::TypeMismatch)
+                .with_arg(name)
+                .with_detail(format!("its type is {}", type_name))),
+            _ => Ok(()),
+        }
+    }
+
+    fn convert<T: FromArg>(name: &str, arg: &Arg) -> Result<T, Error> {
+        T::from_arg(arg).ok_or_else(|| {
+            Error::new(ErrorKind::TypeMismatch)
+                .with_arg(name)
+                .with_detail(format!("its value is {}", arg))
+        })
+    }
+
+    /// How many times the flag was passed on the command line
+    pub fn occurrences(&self, name: &str) -> usize {
+        self.occurrences.get(name).copied().unwrap_or(0)
+    }
+
+    /// Where the value of the argument came from. None if the argument
+    /// wasn't passed and has no environment variable, config file value
+    /// or default value.
+    pub fn source(&self, name: &str) -> Option<ValueSource> {
+        self.sources.get(name).copied()
+    }
+
+    /// The name and matches of the subcommand that was chosen, if any
+    pub fn subcommand(&self) -> Option<(&str, &Matches)> {
+        self.subcommand
+            .as_deref()
+            .map(|sub| (sub.command.as_str(), sub))
+    }
+
+    /// The names of the chosen subcommands from outermost to innermost,
+    /// e.g. ["remote", "add"] for "tool remote add"
+    pub fn subcommand_path(&self) -> Vec<&str> {
+        let mut path = vec![];
+        let mut matches = self;
+        while let Some((name, sub)) = matches.subcommand() {
+            path.push(name);
+            matches = sub;
+        }
+        path
+    }
+}
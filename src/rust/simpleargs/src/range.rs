@@ -0,0 +1,91 @@
+use std::{cmp::Ordering, fmt, ops::Bound};
+
+use super::Arg;
+
+/// The allowed values of a numeric flag or positional argument
+pub(crate) struct ArgRange {
+    pub(crate) start: Bound<Arg>,
+    pub(crate) end: Bound<Arg>,
+}
+
+/// A numeric Arg widened so values of different types can be compared
+enum Number {
+    Integer(i128),
+    Float(f64),
+}
+
+impl Number {
+    fn from_arg(arg: &Arg) -> Option<Self> {
+        match arg {
+            Arg::Float(val) => Some(Number::Float(f64::from(*val))),
+            Arg::Double(val) => Some(Number::Float(*val)),
+            Arg::Integer(val) => Some(Number::Integer(i128::from(*val))),
+            Arg::Integer64(val) => Some(Number::Integer(i128::from(*val))),
+            Arg::Unsigned8(val) => Some(Number::Integer(i128::from(*val))),
+            Arg::Unsigned16(val) => Some(Number::Integer(i128::from(*val))),
+            Arg::Unsigned32(val) => Some(Number::Integer(i128::from(*val))),
+            Arg::Unsigned64(val) => Some(Number::Integer(i128::from(*val))),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> f64 {
+        match self {
+            Number::Integer(val) => *val as f64,
+            Number::Float(val) => *val,
+        }
+    }
+
+    fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Integer(val), Number::Integer(other)) => Some(val.cmp(other)),
+            _ => self.as_f64().partial_cmp(&other.as_f64()),
+        }
+    }
+}
+
+impl ArgRange {
+    /// Whether value is within the range. Non-numeric values always are.
+    pub(crate) fn contains(&self, value: &Arg) -> bool {
+        let value = match Number::from_arg(value) {
+            Some(value) => value,
+            None => return true,
+        };
+
+        // Compare value to the bound, treating a non-numeric bound as unbounded
+        let compare = |bound: &Arg| Number::from_arg(bound).and_then(|b| value.partial_cmp(&b));
+        let above_start = match &self.start {
+            Bound::Included(start) => compare(start).is_none_or(|ord| ord != Ordering::Less),
+            Bound::Excluded(start) => compare(start).is_none_or(|ord| ord == Ordering::Greater),
+            Bound::Unbounded => true,
+        };
+        let below_end = match &self.end {
+            Bound::Included(end) => compare(end).is_none_or(|ord| ord != Ordering::Greater),
+            Bound::Excluded(end) => compare(end).is_none_or(|ord| ord == Ordering::Less),
+            Bound::Unbounded => true,
+        };
+
+        above_start && below_end
+    }
+}
+
+impl fmt::Display for ArgRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let start = match &self.start {
+            Bound::Included(start) => Some(format!(">= {}", start)),
+            Bound::Excluded(start) => Some(format!("> {}", start)),
+            Bound::Unbounded => None,
+        };
+        let end = match &self.end {
+            Bound::Included(end) => Some(format!("<= {}", end)),
+            Bound::Excluded(end) => Some(format!("< {}", end)),
+            Bound::Unbounded => None,
+        };
+
+        match (start, end) {
+            (Some(start), Some(end)) => write!(f, "must be {} and {}", start, end),
+            (Some(bound), None) | (None, Some(bound)) => write!(f, "must be {}", bound),
+            (None, None) => write!(f, "any value is allowed"),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::{fs, path::PathBuf};
+
+use super::{Error, ErrorKind, Parser};
+
+/// Split the contents of a response file into words like a POSIX shell:
+/// words are separated by whitespace, '...' is taken literally, "..."
+/// allows backslash escapes of \" \\ \$ and \`, a backslash outside of
+/// quotes escapes the next character and a word starting with '#'
+/// comments out the rest of the line. Returns the line and message of
+/// an unterminated quote.
+fn split_words(text: &str) -> Result<Vec<String>, (usize, String)> {
+    let mut words = vec![];
+    // None between words, so that '' can be an empty word
+    let mut word: Option<String> = None;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => {
+                line += 1;
+                words.extend(word.take());
+            }
+            c if c.is_whitespace() => words.extend(word.take()),
+            '#' if word.is_none() => while chars.next_if(|c| *c != '\n').is_some() {},
+            '\'' => {
+                let start = line;
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => {
+                            line += usize::from(c == '\n');
+                            word.push(c);
+                        }
+                        None => return Err((start, "unterminated single quote".to_string())),
+                    }
+                }
+            }
+            '"' => {
+                let start = line;
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            // A backslash before a newline joins the lines
+                            Some('\n') => line += 1,
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err((start, "unterminated double quote".to_string())),
+                        },
+                        Some(c) => {
+                            line += usize::from(c == '\n');
+                            word.push(c);
+                        }
+                        None => return Err((start, "unterminated double quote".to_string())),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') => line += 1,
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => word.get_or_insert_with(String::new).push('\\'),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+impl Parser {
+    /// Replace arguments of the form "@path" with the arguments in the
+    /// file at path, e.g. "ping @hosts.txt". The file is split into words
+    /// like a shell would, with '...' and "..." quotes, backslash escapes
+    /// and '#' comments, and may include other response files. Relative
+    /// paths are relative to the working directory. Arguments after "--"
+    /// are never expanded.
+    pub fn response_files(mut self, response_files: bool) -> Self {
+        self.response_files = response_files;
+        self
+    }
+
+    /// The command line arguments, other than the command, with response
+    /// files expanded. Each argument is paired with its argv index, which
+    /// for the contents of a response file is the index of the "@path"
+    /// argument that included it.
+    pub(crate) fn expand_response_files(
+        &self,
+        input_args: impl Iterator<Item = String>,
+    ) -> Result<Vec<(usize, String)>, Error> {
+        let mut expanded = vec![];
+        let mut options_ended = false;
+        for (index, arg) in input_args.enumerate().map(|(i, arg)| (i + 1, arg)) {
+            self.expand_arg(arg, index, &mut vec![], &mut options_ended, &mut expanded)?;
+        }
+        Ok(expanded)
+    }
+
+    /// Push arg to expanded, or the arguments in it if it names a response
+    /// file. including holds the response files being expanded, to detect
+    /// files including themselves.
+    fn expand_arg(
+        &self,
+        arg: String,
+        index: usize,
+        including: &mut Vec<PathBuf>,
+        options_ended: &mut bool,
+        expanded: &mut Vec<(usize, String)>,
+    ) -> Result<(), Error> {
+        let path = match arg.strip_prefix('@') {
+            Some(path) if self.response_files && !*options_ended && !path.is_empty() => {
+                PathBuf::from(path)
+            }
+            _ => {
+                *options_ended |= arg == "--";
+                expanded.push((index, arg));
+                return Ok(());
+            }
+        };
+
+        let error = |detail: String| {
+            Error::new(ErrorKind::ResponseFile)
+                .with_token(&arg)
+                .with_index(index)
+                .with_detail(detail)
+        };
+        let canonical = fs::canonicalize(&path).map_err(|e| error(e.to_string()))?;
+        if including.contains(&canonical) {
+            return Err(error("the file includes itself".to_string()));
+        }
+        let text = fs::read_to_string(&path).map_err(|e| error(e.to_string()))?;
+        let words = split_words(&text).map_err(|(line, detail)| {
+            error(detail).with_location(&path.display().to_string(), Some(line))
+        })?;
+
+        including.push(canonical);
+        for word in words {
+            self.expand_arg(word, index, including, options_ended, expanded)?;
+        }
+        including.pop();
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+/// The edit distance between a and b: how many characters must be
+/// inserted, deleted, substituted or swapped with their neighbour to turn
+/// one into the other (optimal string alignment distance)
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // distances[i][j] is the distance between a[..i] and b[..j]
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j - 1] + cost)
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// "did you mean 'x'?" naming the candidates closest to unknown, if any
+/// are close enough. Leading dashes are ignored, so "--v" suggests "-v",
+/// and so is case, so "-V" suggests "-v".
+pub(crate) fn did_you_mean<'a>(
+    unknown: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Option<String> {
+    let name = unknown.trim_start_matches('-').to_lowercase();
+    // Allow a typo for every three characters
+    let max_distance = name.chars().count() / 3;
+
+    let mut closest: Vec<(usize, &str)> = candidates
+        .map(|candidate| {
+            let candidate_name = candidate.trim_start_matches('-').to_lowercase();
+            (edit_distance(&name, &candidate_name), candidate)
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    closest.sort();
+    closest.dedup();
+
+    let best = closest.first()?.0;
+    let suggestions: Vec<String> = closest
+        .iter()
+        .take_while(|(distance, _)| *distance == best)
+        .take(3)
+        .map(|(_, candidate)| format!("'{}'", candidate))
+        .collect();
+    Some(format!("did you mean {}?", suggestions.join(" or ")))
+}
@@ -1,4 +1,4 @@
-use super::ArgConfig;
+use super::{value_parser::parse_integer_literal, Action, ArgConfig};
 
 pub fn add_dashes_to_long_flag(long_flag: &str) -> String {
     let mut flag = "--".to_string();
@@ -12,21 +12,55 @@ pub fn add_dash_to_short_flag(short_flag: char) -> String {
     flag
 }
 
-pub fn validate_flag_config(_arg_config: &ArgConfig) -> bool {
+pub fn validate_flag_config(arg_config: &ArgConfig) -> bool {
+    if !validate_config(arg_config) {
+        return false;
+    }
+
     // If neither long or short flags were specified, return error
-    /*
-    if let (None, None) = (&flag_config.long_flag, &flag_config.short_flag) {
-        return Err(ParserError::FlagConfigError);
+    if let (None, None) = (&arg_config.long_flag, &arg_config.short_flag) {
+        return false;
+    }
+
+    // Only flags with a value can be appended or split
+    if arg_config.action == Action::Append && arg_config.arg_type.is_none() {
+        return false;
+    }
+    if arg_config.delimiter.is_some() && arg_config.action != Action::Append {
+        return false;
+    }
+
+    // Only boolean flags can be counted or negated, and only long flags
+    // have a "--no-" form
+    if arg_config.action == Action::Count && arg_config.arg_type.is_some() {
+        return false;
+    }
+    if arg_config.negatable
+        && (arg_config.arg_type.is_some()
+            || arg_config.long_flag.is_none()
+            || arg_config.action != Action::Set)
+    {
+        return false;
     }
-     */
 
     // Validate flag allowed characters
 
     true
 }
 
-pub fn validate_arg_config(_arg_config: &ArgConfig) -> bool {
-    true
+/// Checks that apply to both flags and positional arguments
+pub fn validate_config(arg_config: &ArgConfig) -> bool {
+    // A required arg never falls back to its default value
+    !(arg_config.required && arg_config.default_value.is_some())
+}
+
+pub fn validate_arg_config(arg_config: &ArgConfig) -> bool {
+    // Positional arguments always have a value and can't be global
+    validate_config(arg_config)
+        && arg_config.arg_type.is_some()
+        && !arg_config.global
+        && arg_config.action != Action::Count
+        && !arg_config.negatable
 }
 
 /*
@@ -46,6 +80,31 @@ pub fn is_flag(arg: &str) -> bool {
     arg.starts_with('-')
 }
 
+/// Whether arg is a negative number such as "-5", "-0.5" or "-0x1F"
+pub fn is_negative_number(arg: &str) -> bool {
+    match arg.strip_prefix('-') {
+        Some(number) => {
+            number.starts_with(|c: char| c.is_ascii_digit() || c == '.') && is_number(number)
+        }
+        None => false,
+    }
+}
+
+/// Whether value is an integer or floating point number, e.g. "-5",
+/// "0x1F" or "2.5e3"
+pub fn is_number(value: &str) -> bool {
+    value.parse::<f64>().is_ok() || parse_integer_literal(value).is_ok()
+}
+
+/// Parse the usual spellings of true and false, e.g. "yes" or "off"
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "" | "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
 pub fn is_short_flag(arg: &str) -> bool {
     arg.starts_with('-')
 }
@@ -0,0 +1,349 @@
+use std::{any::Any, fmt, marker::PhantomData, rc::Rc, str::FromStr};
+
+use super::{suggest, Arg, ValueEnum};
+
+/// Parses the string value of a flag or positional argument.
+///
+/// The built-in ArgTypes are implemented with this trait. Implement it and
+/// pass it to ArgType::custom to accept your own types of values:
+///
+/// ```
+/// use std::time::Duration;
+/// use simpleargs::{Arg, ArgType, ValueParser};
+///
+/// /// Parses durations such as "500ms" or "2s"
+/// struct DurationParser;
+///
+/// impl ValueParser for DurationParser {
+///     fn parse(&self, value: &str) -> Result<Arg, String> {
+///         let (number, to_duration): (&str, fn(u64) -> Duration) =
+///             match value.strip_suffix("ms") {
+///                 Some(number) => (number, Duration::from_millis),
+///                 None => match value.strip_suffix('s') {
+///                     Some(number) => (number, Duration::from_secs),
+///                     None => return Err("expected a unit of ms or s".to_string()),
+///                 },
+///             };
+///         let number = number.parse::<u64>().map_err(|e| e.to_string())?;
+///         Ok(Arg::custom(to_duration(number), value.to_string()))
+///     }
+///
+///     fn type_name(&self) -> String {
+///         "Duration".to_string()
+///     }
+/// }
+///
+/// let arg_type = ArgType::custom(DurationParser);
+/// assert!(arg_type.value_parser().parse("2s").is_ok());
+/// assert!(arg_type.value_parser().parse("2").is_err());
+/// ```
+pub trait ValueParser {
+    /// Parse the value, or return a message explaining why it is invalid
+    fn parse(&self, value: &str) -> Result<Arg, String>;
+
+    /// The name of the type of value shown in help screens and errors
+    fn type_name(&self) -> String;
+
+    /// The complete list of accepted values, if there is one. Shell
+    /// completions suggest them.
+    fn possible_values(&self) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// Parses a single character into Arg::Character
+pub struct CharacterParser;
+
+impl ValueParser for CharacterParser {
+    fn parse(&self, value: &str) -> Result<Arg, String> {
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(val), None) => Ok(Arg::Character(val)),
+            _ => Err("expected a single character".to_string()),
+        }
+    }
+
+    fn type_name(&self) -> String {
+        "Character".to_string()
+    }
+}
+
+/// Implement ValueParser for a floating point type
+macro_rules! float_parser {
+    ($(#[$doc:meta])* $parser:ident, $float:ty, $variant:ident) => {
+        $(#[$doc])*
+        pub struct $parser;
+
+        impl ValueParser for $parser {
+            fn parse(&self, value: &str) -> Result<Arg, String> {
+                value
+                    .parse::<$float>()
+                    .map(Arg::$variant)
+                    .map_err(|_| "expected a number".to_string())
+            }
+
+            fn type_name(&self) -> String {
+                stringify!($variant).to_string()
+            }
+        }
+    };
+}
+
+float_parser!(
+    /// Parses an f32 into Arg::Float
+    FloatParser, f32, Float
+);
+float_parser!(
+    /// Parses an f64 into Arg::Double
+    DoubleParser, f64, Double
+);
+
+/// Parse an integer literal in decimal, or in hex, octal or binary with a
+/// 0x, 0o or 0b prefix, e.g. "31", "0x1F", "0o37", "0b11111" or "-0x1F"
+pub(crate) fn parse_integer_literal(value: &str) -> Result<i128, String> {
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    let prefix = digits.get(..2).map(|prefix| prefix.to_ascii_lowercase());
+    let (radix, digits) = match prefix.as_deref() {
+        Some("0x") => (16, &digits[2..]),
+        Some("0o") => (8, &digits[2..]),
+        Some("0b") => (2, &digits[2..]),
+        _ => (10, digits),
+    };
+
+    // from_str_radix accepts a sign, which was already stripped
+    if digits.starts_with(['+', '-']) {
+        return Err("expected an integer".to_string());
+    }
+
+    let magnitude =
+        i128::from_str_radix(digits, radix).map_err(|e| format!("expected an integer ({})", e))?;
+    Ok(if negative { -magnitude } else { magnitude })
+}
+
+/// Implement ValueParser for an integer type
+macro_rules! integer_parser {
+    ($(#[$doc:meta])* $parser:ident, $int:ty, $variant:ident) => {
+        $(#[$doc])*
+        pub struct $parser;
+
+        impl ValueParser for $parser {
+            fn parse(&self, value: &str) -> Result<Arg, String> {
+                let val = parse_integer_literal(value)?;
+                <$int>::try_from(val).map(Arg::$variant).map_err(|_| {
+                    format!(
+                        "expected an integer from {} to {}",
+                        <$int>::MIN,
+                        <$int>::MAX
+                    )
+                })
+            }
+
+            fn type_name(&self) -> String {
+                stringify!($variant).to_string()
+            }
+        }
+    };
+}
+
+integer_parser!(
+    /// Parses an i32 into Arg::Integer
+    IntegerParser, i32, Integer
+);
+integer_parser!(
+    /// Parses an i64 into Arg::Integer64
+    Integer64Parser, i64, Integer64
+);
+integer_parser!(
+    /// Parses a u8 into Arg::Unsigned8
+    Unsigned8Parser, u8, Unsigned8
+);
+integer_parser!(
+    /// Parses a u16 into Arg::Unsigned16
+    Unsigned16Parser, u16, Unsigned16
+);
+integer_parser!(
+    /// Parses a u32 into Arg::Unsigned32
+    Unsigned32Parser, u32, Unsigned32
+);
+integer_parser!(
+    /// Parses a u64 into Arg::Unsigned64
+    Unsigned64Parser, u64, Unsigned64
+);
+
+/// Accepts any value as Arg::String
+pub struct StringParser;
+
+impl ValueParser for StringParser {
+    fn parse(&self, value: &str) -> Result<Arg, String> {
+        Ok(Arg::String(value.to_owned()))
+    }
+
+    fn type_name(&self) -> String {
+        "String".to_string()
+    }
+}
+
+/// Accepts one of a fixed list of values as Arg::String, e.g. "--format
+/// json" out of text, json and csv. Help screens and shell completions
+/// list the choices.
+///
+/// ```
+/// use simpleargs::{ArgType, ChoiceParser, ValueParser};
+///
+/// let parser = ChoiceParser::new(&["text", "json", "csv"]).ignore_case(true);
+/// assert_eq!(parser.parse("JSON").unwrap(), "json".into());
+/// assert!(parser.parse("xml").is_err());
+///
+/// // The case sensitive choices as an ArgType
+/// let arg_type = ArgType::choices(&["text", "json", "csv"]);
+/// ```
+pub struct ChoiceParser {
+    choices: Vec<String>,
+    ignore_case: bool,
+}
+
+impl ChoiceParser {
+    /// Accept exactly one of choices
+    pub fn new(choices: &[&str]) -> Self {
+        ChoiceParser {
+            choices: choices.iter().map(|choice| choice.to_string()).collect(),
+            ignore_case: false,
+        }
+    }
+
+    /// Accept the variants of the ValueEnum T, read back with
+    /// Matches::get::<T>
+    pub fn of<T: ValueEnum>() -> Self {
+        let variants = T::variants();
+        let choices: Vec<&str> = variants.iter().map(|(name, _)| *name).collect();
+        Self::new(&choices)
+    }
+
+    /// Also accept the choices in any case, e.g. "JSON" for "json". The
+    /// value is stored spelled as in the choices.
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+}
+
+impl ValueParser for ChoiceParser {
+    fn parse(&self, value: &str) -> Result<Arg, String> {
+        let choice = self.choices.iter().find(|choice| {
+            if self.ignore_case {
+                choice.to_lowercase() == value.to_lowercase()
+            } else {
+                *choice == value
+            }
+        });
+        if let Some(choice) = choice {
+            return Ok(Arg::String(choice.clone()));
+        }
+
+        let mut message = match &self.choices[..] {
+            [] => "there are no valid values".to_string(),
+            [choice] => format!("expected {}", choice),
+            [choices @ .., last] => format!("expected one of {} or {}", choices.join(", "), last),
+        };
+        if let Some(suggestion) =
+            suggest::did_you_mean(value, self.choices.iter().map(String::as_str))
+        {
+            message.push_str(", ");
+            message.push_str(&suggestion);
+        }
+        Err(message)
+    }
+
+    fn type_name(&self) -> String {
+        "Choice".to_string()
+    }
+
+    fn possible_values(&self) -> Vec<String> {
+        self.choices.clone()
+    }
+}
+
+/// Parses any type implementing FromStr into an Arg::Custom holding it,
+/// e.g. IP addresses or socket addresses.
+///
+/// ```
+/// use std::net::IpAddr;
+/// use simpleargs::{ArgType, FromStrParser};
+///
+/// let arg_type = ArgType::custom(FromStrParser::<IpAddr>::new("IP address"));
+/// ```
+pub struct FromStrParser<T> {
+    type_name: String,
+    value_type: PhantomData<T>,
+}
+
+impl<T> FromStrParser<T> {
+    /// type_name is shown in help screens and errors
+    pub fn new(type_name: &str) -> Self {
+        FromStrParser {
+            type_name: type_name.to_owned(),
+            value_type: PhantomData,
+        }
+    }
+}
+
+impl<T> ValueParser for FromStrParser<T>
+where
+    T: FromStr + fmt::Display + 'static,
+    T::Err: fmt::Display,
+{
+    fn parse(&self, value: &str) -> Result<Arg, String> {
+        match value.parse::<T>() {
+            Ok(val) => {
+                let display = val.to_string();
+                Ok(Arg::custom(val, display))
+            }
+            Err(e) => Err(format!("expected {} ({})", self.type_name, e)),
+        }
+    }
+
+    fn type_name(&self) -> String {
+        self.type_name.clone()
+    }
+}
+
+/// A value produced by a custom ValueParser, stored in Arg::Custom
+#[derive(Clone)]
+pub struct CustomValue {
+    value: Rc<dyn Any>,
+    /// How the value is shown in help screens and converted to a string
+    display: String,
+}
+
+impl CustomValue {
+    pub(crate) fn new(value: Rc<dyn Any>, display: String) -> Self {
+        CustomValue { value, display }
+    }
+
+    /// The value if it is of type T
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        self.value.downcast_ref::<T>()
+    }
+}
+
+impl PartialEq for CustomValue {
+    fn eq(&self, other: &Self) -> bool {
+        (*self.value).type_id() == (*other.value).type_id() && self.display == other.display
+    }
+}
+
+impl fmt::Debug for CustomValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.display)
+    }
+}
+
+impl fmt::Display for CustomValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display)
+    }
+}
@@ -0,0 +1,343 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Expr, Fields, GenericArgument, Ident, Lit,
+    LitChar, LitStr, PathArguments, Type,
+};
+
+/// How a field's Rust type maps onto an ArgConfig
+enum FieldKind<'a> {
+    /// bool: a flag without a value
+    Boolean,
+    /// Option<T>: an optional value
+    Optional(&'a Type),
+    /// Vec<T>: an Append flag or variadic positional
+    List(&'a Type),
+    /// T: a required value, unless it has a default
+    Required(&'a Type),
+}
+
+/// The settings from a field's #[simpleargs(...)] attributes
+#[derive(Default)]
+struct FieldAttrs {
+    long: Option<LitStr>,
+    short: Option<LitChar>,
+    positional: bool,
+    env: Option<LitStr>,
+    default: Option<Lit>,
+    allow_hyphen_values: bool,
+    count: bool,
+    negatable: bool,
+    requires: Vec<LitStr>,
+    conflicts_with: Vec<LitStr>,
+}
+
+/// Derive simpleargs::SimpleArgs for a struct with named fields.
+///
+/// See the README of this crate for how fields are mapped onto flags.
+#[proc_macro_derive(SimpleArgs, attributes(simpleargs))]
+pub fn derive_simple_args(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    input,
+                    "SimpleArgs requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "SimpleArgs can only be derived for structs",
+            ))
+        }
+    };
+
+    let struct_name = &input.ident;
+    let description = doc_comment(&input.attrs).unwrap_or_else(|| struct_name.to_string());
+
+    let mut arg_configs = vec![];
+    let mut field_values = vec![];
+    for field in fields {
+        // Unwrap is safe here because the fields are named
+        let ident = field.ident.as_ref().unwrap();
+        let attrs = field_attrs(&field.attrs)?;
+        let kind = field_kind(&field.ty);
+        arg_configs.push(arg_config(ident, &field.attrs, &attrs, &kind)?);
+        field_values.push(field_value(ident, &attrs, &kind));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::simpleargs::SimpleArgs for #struct_name #ty_generics #where_clause {
+            fn parser() -> ::simpleargs::Parser {
+                ::simpleargs::new(#description.to_string())
+                    #(.add_arg(#arg_configs))*
+            }
+
+            fn from_matches(
+                matches: &::simpleargs::Matches,
+            ) -> ::std::result::Result<Self, ::simpleargs::Error> {
+                ::std::result::Result::Ok(#struct_name {
+                    #(#field_values,)*
+                })
+            }
+        }
+    })
+}
+
+/// Build the expression creating the field's ArgConfig
+fn arg_config(
+    ident: &Ident,
+    raw_attrs: &[Attribute],
+    attrs: &FieldAttrs,
+    kind: &FieldKind,
+) -> syn::Result<TokenStream2> {
+    let name = ident.to_string();
+    let description = doc_comment(raw_attrs).unwrap_or_default();
+    let mut config = quote! {
+        ::simpleargs::ArgConfig::new(#name.to_string())
+            .description(#description.to_string())
+    };
+
+    if attrs.positional {
+        if let FieldKind::Boolean = kind {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "a bool field is a flag and can't be positional",
+            ));
+        }
+    } else {
+        let long = attrs
+            .long
+            .as_ref()
+            .map(|long| long.value())
+            .unwrap_or_else(|| name.clone());
+        config.extend(quote! { .long_flag(#long.to_string()) });
+        if let Some(short) = &attrs.short {
+            config.extend(quote! { .short_flag(#short) });
+        }
+    }
+
+    match kind {
+        _ if attrs.count => {
+            if attrs.positional {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "a counted field is a flag and can't be positional",
+                ));
+            }
+            config.extend(quote! { .action(::simpleargs::Action::Count) });
+        }
+        FieldKind::Boolean => {}
+        FieldKind::Optional(ty) => {
+            let arg_type = arg_type(ty);
+            config.extend(quote! { .arg_type(#arg_type) });
+        }
+        FieldKind::List(ty) => {
+            let arg_type = arg_type(ty);
+            config.extend(quote! {
+                .arg_type(#arg_type)
+                .action(::simpleargs::Action::Append)
+            });
+        }
+        FieldKind::Required(ty) => {
+            let arg_type = arg_type(ty);
+            let required = attrs.default.is_none();
+            config.extend(quote! {
+                .arg_type(#arg_type)
+                .required(#required)
+            });
+        }
+    }
+
+    // Shell completions suggest file names for paths
+    if let FieldKind::Optional(ty) | FieldKind::List(ty) | FieldKind::Required(ty) = kind {
+        if type_name(ty).as_deref() == Some("PathBuf") {
+            config.extend(quote! { .value_hint(::simpleargs::ValueHint::FilePath) });
+        }
+    }
+
+    if attrs.negatable {
+        config.extend(quote! { .negatable(true) });
+    }
+    if attrs.allow_hyphen_values {
+        config.extend(quote! { .allow_hyphen_values(true) });
+    }
+    if let Some(env) = &attrs.env {
+        config.extend(quote! { .env(#env.to_string()) });
+    }
+    if let Some(default) = &attrs.default {
+        config.extend(quote! { .default_value(::simpleargs::Arg::from(#default)) });
+    }
+    for requires in &attrs.requires {
+        config.extend(quote! { .requires(#requires.to_string()) });
+    }
+    for conflicts_with in &attrs.conflicts_with {
+        config.extend(quote! { .conflicts_with(#conflicts_with.to_string()) });
+    }
+
+    Ok(config)
+}
+
+/// Build the "field: value" initializer reading the field from the matches
+fn field_value(ident: &Ident, attrs: &FieldAttrs, kind: &FieldKind) -> TokenStream2 {
+    let name = ident.to_string();
+    let display_name = if attrs.positional {
+        format!("<{}>", name)
+    } else {
+        let long = attrs
+            .long
+            .as_ref()
+            .map(|long| long.value())
+            .unwrap_or(name.clone());
+        format!("--{}", long)
+    };
+
+    let support = quote! { ::simpleargs::derive_support };
+    match kind {
+        FieldKind::Boolean => quote! {
+            #ident: #support::get_flag(matches, #name)
+        },
+        FieldKind::Optional(ty) => quote! {
+            #ident: #support::get_value::<#ty>(matches, #name, #display_name)?
+        },
+        FieldKind::List(ty) => quote! {
+            #ident: #support::get_values::<#ty>(matches, #name, #display_name)?
+        },
+        FieldKind::Required(ty) => quote! {
+            #ident: #support::get_required::<#ty>(matches, #name, #display_name)?
+        },
+    }
+}
+
+/// Pick the ArgType validating the value at parse time. Types the
+/// built-in ArgTypes can't represent are parsed as strings and converted
+/// with FromStr when the struct is filled.
+fn arg_type(ty: &Type) -> TokenStream2 {
+    let variant = match type_name(ty).as_deref() {
+        Some("i8" | "i16" | "i32") => quote! { Integer },
+        Some("i64") => quote! { Integer64 },
+        Some("u8") => quote! { Unsigned8 },
+        Some("u16") => quote! { Unsigned16 },
+        Some("u32") => quote! { Unsigned32 },
+        Some("u64") => quote! { Unsigned64 },
+        Some("f32") => quote! { Float },
+        Some("f64") => quote! { Double },
+        Some("char") => quote! { Character },
+        _ => quote! { String },
+    };
+    quote! { ::simpleargs::ArgType::#variant }
+}
+
+fn field_kind(ty: &Type) -> FieldKind<'_> {
+    match type_name(ty).as_deref() {
+        Some("bool") => FieldKind::Boolean,
+        Some("Option") => match generic_argument(ty) {
+            Some(inner) => FieldKind::Optional(inner),
+            None => FieldKind::Required(ty),
+        },
+        Some("Vec") => match generic_argument(ty) {
+            Some(inner) => FieldKind::List(inner),
+            None => FieldKind::Required(ty),
+        },
+        _ => FieldKind::Required(ty),
+    }
+}
+
+/// The last segment of a type's path, e.g. "Option" for std::option::Option<T>
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// The T in Option<T> or Vec<T>
+fn generic_argument(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(type_path) => type_path.path.segments.last()?,
+        _ => return None,
+    };
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Join the lines of the /// comments into a single description
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta.require_name_value().ok()?.value {
+            Expr::Lit(expr) => match &expr.lit {
+                Lit::Str(doc) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join(" "))
+    }
+}
+
+fn field_attrs(attrs: &[Attribute]) -> syn::Result<FieldAttrs> {
+    let mut field_attrs = FieldAttrs::default();
+    for attr in attrs {
+        if !attr.path().is_ident("simpleargs") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("long") {
+                field_attrs.long = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("short") {
+                field_attrs.short = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("positional") {
+                field_attrs.positional = true;
+            } else if meta.path.is_ident("env") {
+                field_attrs.env = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("allow_hyphen_values") {
+                field_attrs.allow_hyphen_values = true;
+            } else if meta.path.is_ident("count") {
+                field_attrs.count = true;
+            } else if meta.path.is_ident("negatable") {
+                field_attrs.negatable = true;
+            } else if meta.path.is_ident("requires") {
+                field_attrs.requires.push(meta.value()?.parse()?);
+            } else if meta.path.is_ident("conflicts_with") {
+                field_attrs.conflicts_with.push(meta.value()?.parse()?);
+            } else if meta.path.is_ident("default") {
+                field_attrs.default = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error("unknown simpleargs attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(field_attrs)
+}
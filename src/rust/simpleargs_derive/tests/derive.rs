@@ -0,0 +1,138 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use simpleargs::{ErrorKind, SimpleArgs};
+
+#[derive(Debug, PartialEq)]
+enum Format {
+    Text,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format {}", s)),
+        }
+    }
+}
+
+/// Send ICMP echo requests
+#[derive(SimpleArgs, Debug)]
+struct Args {
+    /// Stop after sending count packets
+    #[simpleargs(short = 'c')]
+    count: Option<u16>,
+
+    /// Print only the summary
+    #[simpleargs(short = 'q')]
+    quiet: bool,
+
+    /// Print the replies as JSON
+    #[simpleargs(conflicts_with = "quiet")]
+    json: bool,
+
+    /// Print more details, repeat for even more
+    #[simpleargs(short = 'v', count)]
+    verbose: u8,
+
+    /// Colorize the output
+    #[simpleargs(negatable)]
+    color: bool,
+
+    /// Seconds between packets
+    #[simpleargs(short = 'i', default = 1.0)]
+    interval: f32,
+
+    /// Output format
+    #[simpleargs(long = "format")]
+    output_format: Option<Format>,
+
+    /// Extra headers
+    #[simpleargs(short = 'H')]
+    header: Vec<String>,
+
+    /// The host to ping
+    #[simpleargs(positional)]
+    destination: IpAddr,
+}
+
+fn try_parse(args: &[&str]) -> Result<Args, simpleargs::Error> {
+    Args::try_parse(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn test_derive_fills_struct() {
+    let args = try_parse(&[
+        "ping",
+        "-c",
+        "5",
+        "-q",
+        "--format=json",
+        "-H",
+        "a",
+        "-Hb",
+        "::1",
+    ])
+    .unwrap();
+
+    assert_eq!(args.count, Some(5));
+    assert!(args.quiet);
+    assert!(!args.json);
+    assert_eq!(args.interval, 1.0);
+    assert_eq!(args.output_format, Some(Format::Json));
+    assert_eq!(args.header, vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(args.destination, "::1".parse::<IpAddr>().unwrap());
+
+    let args = try_parse(&["ping", "127.0.0.1"]).unwrap();
+    assert_eq!(args.count, None);
+    assert!(!args.quiet);
+    assert_eq!(args.output_format, None);
+    assert!(args.header.is_empty());
+    assert_eq!(args.verbose, 0);
+    assert!(!args.color);
+
+    let args = try_parse(&["ping", "-vvv", "--color", "-v", "::1"]).unwrap();
+    assert_eq!(args.verbose, 4);
+    assert!(args.color);
+    let args = try_parse(&["ping", "--color", "--no-color", "::1"]).unwrap();
+    assert!(!args.color);
+}
+
+#[test]
+fn test_derive_errors() {
+    let e = try_parse(&["ping"]).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::MissingRequired);
+    assert_eq!(e.token(), Some("<destination>"));
+
+    let e = try_parse(&["ping", "not_an_ip"]).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+    assert_eq!(e.arg(), Some("<destination>"));
+
+    // Out of range for u16
+    let e = try_parse(&["ping", "-c", "70000", "::1"]).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+    assert_eq!(e.arg(), Some("--count"));
+
+    let e = try_parse(&["ping", "--format=xml", "::1"]).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::IncorrectArgType);
+    assert_eq!(e.token(), Some("xml"));
+
+    let e = try_parse(&["ping", "--json", "-q", "::1"]).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::ArgumentConflict);
+    assert_eq!(e.token(), Some("--json"));
+    assert_eq!(e.arg(), Some("--quiet"));
+}
+
+#[test]
+fn test_derive_help_uses_doc_comments() {
+    let e = try_parse(&["ping", "--help"]).unwrap_err();
+    let help = e.help().unwrap();
+    assert!(help.contains("Send ICMP echo requests"));
+    assert!(help.contains("Stop after sending count packets"));
+    assert!(help.contains("-c"));
+}